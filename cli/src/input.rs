@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use clap::ValueEnum;
+use docfind_core::{InputItem, SearchTokens, StreamingIndexBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+	/// A JSON array of items
+	Json,
+	/// One JSON item per line
+	Ndjson,
+	/// A CSV file with a header row
+	Csv,
+}
+
+impl Format {
+	/// Guesses the format from the file extension.
+	pub fn detect(path: &Path) -> Result<Format, Box<dyn std::error::Error>> {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("json") => Ok(Format::Json),
+			Some("ndjson" | "jsonl") => Ok(Format::Ndjson),
+			Some("csv") => Ok(Format::Csv),
+			_ => Err(
+				format!(
+					"Cannot detect the format of '{}', pass --format",
+					path.display()
+				)
+				.into(),
+			),
+		}
+	}
+}
+
+/// How CSV rows are turned into items.
+pub struct CsvOptions {
+	pub id_column: String,
+	pub default_weight: u8,
+	pub weights: HashMap<String, u8>,
+	pub facet_columns: HashSet<String>,
+	pub number_columns: HashSet<String>,
+	pub boost_column: Option<String>,
+	pub group_column: Option<String>,
+}
+
+/// Adds every item in `path` to the builder, returning the number of items.
+pub fn add_file(
+	builder: &mut StreamingIndexBuilder,
+	path: &Path,
+	format: Format,
+	csv_options: &CsvOptions,
+) -> Result<usize, Box<dyn std::error::Error>> {
+	let reader = BufReader::new(File::open(path)?);
+
+	match format {
+		Format::Json => {
+			let items: Vec<InputItem> = serde_json::from_reader(reader)?;
+			let count = items.len();
+			for item in items {
+				builder.add(item)?;
+			}
+			Ok(count)
+		}
+		Format::Ndjson => builder.add_ndjson(reader),
+		Format::Csv => add_csv(builder, reader, csv_options),
+	}
+}
+
+/// Every column other than the ID column becomes a raw search term weighted
+/// by its entry in `weights`, or `default_weight`, except facet and number
+/// columns, whose values become facets and numeric attributes instead, and
+/// the boost and group columns.
+fn add_csv(
+	builder: &mut StreamingIndexBuilder,
+	reader: impl std::io::Read,
+	options: &CsvOptions,
+) -> Result<usize, Box<dyn std::error::Error>> {
+	let mut csv = csv::Reader::from_reader(reader);
+	let headers = csv.headers()?.clone();
+	let id_column = headers
+		.iter()
+		.position(|header| header == options.id_column)
+		.ok_or_else(|| format!("CSV has no '{}' column", options.id_column))?;
+
+	let mut count = 0;
+	for record in csv.records() {
+		let record = record?;
+		let mut fields: BTreeMap<String, Vec<_>> = BTreeMap::new();
+		let mut facets = BTreeMap::new();
+		let mut numbers = BTreeMap::new();
+		let mut boost = None;
+		let mut group = None;
+
+		for (column, value) in record.iter().enumerate() {
+			if column == id_column || value.trim().is_empty() {
+				continue;
+			}
+			if options.facet_columns.contains(&headers[column]) {
+				facets.insert(headers[column].to_string(), vec![value.trim().to_string()]);
+				continue;
+			}
+			if options.number_columns.contains(&headers[column]) {
+				numbers.insert(
+					headers[column].to_string(),
+					parse_number(&headers[column], value)?,
+				);
+				continue;
+			}
+			if options.boost_column.as_deref() == Some(&headers[column]) {
+				boost = Some(parse_number(&headers[column], value)? as f32);
+				continue;
+			}
+			if options.group_column.as_deref() == Some(&headers[column]) {
+				group = Some(value.trim().to_string());
+				continue;
+			}
+			let weight = options
+				.weights
+				.get(&headers[column])
+				.copied()
+				.unwrap_or(options.default_weight);
+			fields
+				.entry(headers[column].to_string())
+				.or_default()
+				.push((SearchTokens::Raw(value.to_string()), weight));
+		}
+
+		builder.add(InputItem {
+			id: record[id_column].to_string(),
+			search_terms: Vec::new(),
+			fields,
+			facets,
+			numbers,
+			boost,
+			group,
+		})?;
+		count += 1;
+	}
+
+	Ok(count)
+}
+
+fn parse_number(column: &str, value: &str) -> Result<f64, Box<dyn std::error::Error>> {
+	value
+		.trim()
+		.parse()
+		.map_err(|_| format!("Column '{}' has a non-numeric value '{}'", column, value).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use docfind_core::{Index, build_index};
+
+	fn csv_options() -> CsvOptions {
+		CsvOptions {
+			id_column: "id".to_string(),
+			default_weight: 50,
+			weights: [("title".to_string(), 100)].into(),
+			facet_columns: ["category".to_string()].into(),
+			number_columns: ["price".to_string()].into(),
+			boost_column: Some("popularity".to_string()),
+			group_column: Some("page".to_string()),
+		}
+	}
+
+	fn build(csv: &str) -> Result<Index, Box<dyn std::error::Error>> {
+		let mut builder = StreamingIndexBuilder::new();
+		add_csv(&mut builder, csv.as_bytes(), &csv_options())?;
+		builder.finish()
+	}
+
+	#[test]
+	fn test_csv_columns() {
+		let csv = "\
+id,title,body,category,price,popularity,page
+p-1,Rust Guide,Learn rust,books,29.5,3,/rust
+p-2,Python,,books, 12 ,,/python
+";
+
+		let raw = |value: &str, weight| vec![(SearchTokens::Raw(value.to_string()), weight)];
+		let expected = vec![
+			InputItem {
+				id: "p-1".to_string(),
+				fields: [
+					("title".to_string(), raw("Rust Guide", 100)),
+					("body".to_string(), raw("Learn rust", 50)),
+				]
+				.into(),
+				facets: [("category".to_string(), vec!["books".to_string()])].into(),
+				numbers: [("price".to_string(), 29.5)].into(),
+				boost: Some(3.0),
+				group: Some("/rust".to_string()),
+				..Default::default()
+			},
+			InputItem {
+				id: "p-2".to_string(),
+				fields: [("title".to_string(), raw("Python", 100))].into(),
+				facets: [("category".to_string(), vec!["books".to_string()])].into(),
+				numbers: [("price".to_string(), 12.0)].into(),
+				group: Some("/python".to_string()),
+				..Default::default()
+			},
+		];
+		assert_eq!(
+			build(csv).unwrap().to_bytes().unwrap(),
+			build_index(expected).unwrap().to_bytes().unwrap()
+		);
+	}
+
+	#[test]
+	fn test_csv_errors() {
+		let error = build("id,price\np-1,cheap\n").unwrap_err().to_string();
+		assert_eq!(error, "Column 'price' has a non-numeric value 'cheap'");
+
+		let error = build("id,popularity\np-1,high\n").unwrap_err().to_string();
+		assert_eq!(error, "Column 'popularity' has a non-numeric value 'high'");
+
+		let error = build("sku,title\np-1,Rust\n").unwrap_err().to_string();
+		assert_eq!(error, "CSV has no 'id' column");
+	}
+
+	#[test]
+	fn test_format_detect() {
+		let detect = |path: &str| Format::detect(Path::new(path)).ok();
+		assert_eq!(detect("items.json"), Some(Format::Json));
+		assert_eq!(detect("items.ndjson"), Some(Format::Ndjson));
+		assert_eq!(detect("items.jsonl"), Some(Format::Ndjson));
+		assert_eq!(detect("dir/items.csv"), Some(Format::Csv));
+		assert_eq!(detect("items.txt"), None);
+		assert_eq!(detect("items"), None);
+	}
+}
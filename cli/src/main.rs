@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use docfind_core::{
+	BoostMode, BuildOptions, FacetFilter, Index, KeywordMerge, RangeFilter, SearchOptions, SortKey,
+	StreamingIndexBuilder, SuggestOptions, SuggestRank,
+};
+
+mod input;
+
+use input::{CsvOptions, Format};
+
+/// Build, query and check docfind indexes.
+#[derive(Parser)]
+#[command(name = "docfind", version)]
+struct Cli {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Build an index from JSON, NDJSON or CSV files
+	Build {
+		/// Input files, combined in the given order
+		#[arg(required = true)]
+		inputs: Vec<PathBuf>,
+		/// Where to write the index
+		#[arg(short, long, default_value = "index.bin")]
+		output: PathBuf,
+		/// Input format, detected from the file extension by default
+		#[arg(short, long)]
+		format: Option<Format>,
+		/// Spill postings to disk above this many megabytes
+		#[arg(long)]
+		memory_budget: Option<usize>,
+		/// CSV column holding the item ID
+		#[arg(long, default_value = "id")]
+		id_column: String,
+		/// Weight of CSV columns without an explicit --weight
+		#[arg(long, default_value_t = 100)]
+		default_weight: u8,
+		/// Weight of a CSV column, as COLUMN=WEIGHT
+		#[arg(long = "weight", value_parser = parse_key_value::<u8>)]
+		weights: Vec<(String, u8)>,
+		/// CSV column stored as a facet instead of a search term
+		#[arg(long = "facet-column")]
+		facet_columns: Vec<String>,
+		/// CSV column stored as a numeric attribute instead of a search term
+		#[arg(long = "number-column")]
+		number_columns: Vec<String>,
+		/// CSV column holding the boost of each item
+		#[arg(long)]
+		boost_column: Option<String>,
+		/// CSV column holding the group of each item, for --collapse
+		#[arg(long)]
+		group_column: Option<String>,
+		/// How the weights of a keyword repeated within an item combine:
+		/// first, max, sum or weighted-by-count
+		#[arg(long, default_value = "first", value_parser = parse_keyword_merge)]
+		keyword_merge: KeywordMerge,
+		/// Also index keywords by how they sound, for matching names spelled
+		/// the way they are pronounced
+		#[arg(long)]
+		phonetic: bool,
+	},
+	/// Search an index and print the hits with their scores
+	Search {
+		index: PathBuf,
+		query: String,
+		/// Maximum number of results
+		#[arg(short = 'n', long, default_value_t = 10)]
+		max_results: usize,
+		/// Number of best results to skip
+		#[arg(long, default_value_t = 0)]
+		offset: usize,
+		/// Most keywords each query word may expand to
+		#[arg(long, default_value_t = SearchOptions::default().max_expansions)]
+		max_expansions: usize,
+		/// Shortest query word matched with a typo
+		#[arg(long, default_value_t = SearchOptions::default().min_fuzzy_length)]
+		min_fuzzy_length: usize,
+		/// Shortest query word matched with two typos
+		#[arg(long, default_value_t = SearchOptions::default().min_two_typo_length)]
+		min_two_typo_length: usize,
+		/// Count swapped adjacent characters as two typos instead of one
+		#[arg(long)]
+		no_transpositions: bool,
+		/// Multiplier for the weights of keywords that only sound like a query
+		/// word, in indexes built with --phonetic
+		#[arg(long, default_value_t = SearchOptions::default().phonetic_weight)]
+		phonetic_weight: f32,
+		/// Match words such as prog*ing and /v[0-9]+/ literally instead of as
+		/// wildcard and regular expression patterns
+		#[arg(long)]
+		no_patterns: bool,
+		/// Only return items with this facet value, as FACET=VALUE. Values of
+		/// the same facet are alternatives; different facets must all match
+		#[arg(long = "filter", value_parser = parse_key_value::<String>)]
+		filters: Vec<(String, String)>,
+		/// Print how many matching items have each value of this facet
+		#[arg(long = "facet")]
+		facets: Vec<String>,
+		/// Only return items with a numeric attribute in a range, as
+		/// ATTRIBUTE=MIN..MAX where either bound may be left out
+		#[arg(long = "range", value_parser = parse_range)]
+		ranges: Vec<RangeFilter>,
+		/// Order results by a numeric attribute, as ATTRIBUTE or
+		/// ATTRIBUTE:desc, before their score
+		#[arg(long = "sort", value_parser = parse_sort_key)]
+		sort: Vec<SortKey>,
+		/// How item boosts combine with the query score: none, multiply, add
+		/// or log
+		#[arg(long, default_value = "multiply", value_parser = parse_boost_mode)]
+		boost_mode: BoostMode,
+		/// Multiply the weights of terms from a field, as FIELD=BOOST. Query
+		/// words can be boosted in the query itself, as in "rust^3 guide"
+		#[arg(long = "field-boost", value_parser = parse_key_value::<f32>)]
+		field_boosts: Vec<(String, f32)>,
+		/// Return only the best hit of each group, printing how many others
+		/// it stands for
+		#[arg(long)]
+		collapse: bool,
+		/// Print the total number of matches and how many keywords the query
+		/// expanded to
+		#[arg(long)]
+		stats: bool,
+	},
+	/// Complete the last word of a query, printing the frequency and total
+	/// weight of each completion
+	Suggest {
+		index: PathBuf,
+		query: String,
+		/// Maximum number of suggestions
+		#[arg(short = 'n', long, default_value_t = 10)]
+		max_suggestions: usize,
+		/// What to rank suggestions by: frequency or weight
+		#[arg(long, default_value = "frequency", value_parser = parse_suggest_rank)]
+		rank: SuggestRank,
+	},
+	/// Print statistics about an index
+	Inspect {
+		index: PathBuf,
+		/// Number of most frequent keywords to list
+		#[arg(long, default_value_t = 20)]
+		top: usize,
+		/// Print the statistics as JSON
+		#[arg(long)]
+		json: bool,
+		/// Print every keyword with its postings instead of statistics
+		#[arg(long, conflicts_with = "json")]
+		dump: bool,
+	},
+	/// Check that an index is intact and answers queries as expected
+	Verify {
+		index: PathBuf,
+		/// Require ID among the results for QUERY, as QUERY=ID
+		#[arg(long = "expect", value_parser = parse_key_value::<String>)]
+		expectations: Vec<(String, String)>,
+		/// How many results to look at for each --expect
+		#[arg(short = 'n', long, default_value_t = 10)]
+		max_results: usize,
+	},
+}
+
+fn parse_key_value<T>(arg: &str) -> Result<(String, T), String>
+where
+	T: std::str::FromStr,
+	T::Err: std::fmt::Display,
+{
+	let (key, value) = arg
+		.rsplit_once('=')
+		.ok_or_else(|| format!("expected KEY=VALUE, got '{}'", arg))?;
+	let value = value
+		.parse()
+		.map_err(|e| format!("invalid value '{}': {}", value, e))?;
+	Ok((key.to_string(), value))
+}
+
+fn parse_range(arg: &str) -> Result<RangeFilter, String> {
+	let (attribute, range) = parse_key_value::<String>(arg)?;
+	let (min, max) = range
+		.split_once("..")
+		.ok_or_else(|| format!("expected MIN..MAX, got '{}'", range))?;
+	let bound = |bound: &str| -> Result<Option<f64>, String> {
+		if bound.is_empty() {
+			return Ok(None);
+		}
+		bound
+			.parse()
+			.map(Some)
+			.map_err(|e| format!("invalid bound '{}': {}", bound, e))
+	};
+	Ok(RangeFilter {
+		attribute,
+		min: bound(min)?,
+		max: bound(max)?,
+	})
+}
+
+fn parse_sort_key(arg: &str) -> Result<SortKey, String> {
+	let (attribute, descending) = match arg.rsplit_once(':') {
+		Some((attribute, "desc")) => (attribute, true),
+		Some((attribute, "asc")) => (attribute, false),
+		Some((_, order)) => return Err(format!("expected 'asc' or 'desc', got '{}'", order)),
+		None => (arg, false),
+	};
+	Ok(SortKey {
+		attribute: attribute.to_string(),
+		descending,
+	})
+}
+
+fn parse_keyword_merge(arg: &str) -> Result<KeywordMerge, String> {
+	match arg {
+		"first" => Ok(KeywordMerge::First),
+		"max" => Ok(KeywordMerge::Max),
+		"sum" => Ok(KeywordMerge::Sum),
+		"weighted-by-count" => Ok(KeywordMerge::WeightedByCount),
+		_ => Err(format!(
+			"expected first, max, sum or weighted-by-count, got '{}'",
+			arg
+		)),
+	}
+}
+
+fn parse_boost_mode(arg: &str) -> Result<BoostMode, String> {
+	match arg {
+		"none" => Ok(BoostMode::None),
+		"multiply" => Ok(BoostMode::Multiply),
+		"add" => Ok(BoostMode::Add),
+		"log" => Ok(BoostMode::Log),
+		_ => Err(format!(
+			"expected none, multiply, add or log, got '{}'",
+			arg
+		)),
+	}
+}
+
+fn parse_suggest_rank(arg: &str) -> Result<SuggestRank, String> {
+	match arg {
+		"frequency" => Ok(SuggestRank::Frequency),
+		"weight" => Ok(SuggestRank::Weight),
+		_ => Err(format!("expected frequency or weight, got '{}'", arg)),
+	}
+}
+
+/// Loads the index at `path`, running [`Index::verify`] on it first if
+/// `verify` is set. Searching relies on the checks it makes; inspecting and
+/// verifying an index do not.
+fn load_index(path: &PathBuf, verify: bool) -> Result<Index, Box<dyn std::error::Error>> {
+	let bytes =
+		std::fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+	let index = if verify {
+		Index::from_bytes_verified(&bytes)
+	} else {
+		Index::from_bytes(&bytes)
+	};
+	index.map_err(|e| format!("Failed to deserialize index: {}", e).into())
+}
+
+fn run(command: Command) -> Result<bool, Box<dyn std::error::Error>> {
+	match command {
+		Command::Build {
+			inputs,
+			output,
+			format,
+			memory_budget,
+			id_column,
+			default_weight,
+			weights,
+			facet_columns,
+			number_columns,
+			boost_column,
+			group_column,
+			keyword_merge,
+			phonetic,
+		} => {
+			let builder = match memory_budget {
+				Some(megabytes) => {
+					StreamingIndexBuilder::with_memory_budget(megabytes << 20, std::env::temp_dir())
+				}
+				None => StreamingIndexBuilder::new(),
+			};
+			let mut builder = builder.with_options(BuildOptions {
+				keyword_merge,
+				phonetic,
+			});
+			let csv_options = CsvOptions {
+				id_column,
+				default_weight,
+				weights: weights.into_iter().collect::<HashMap<_, _>>(),
+				facet_columns: facet_columns.into_iter().collect(),
+				number_columns: number_columns.into_iter().collect(),
+				boost_column,
+				group_column,
+			};
+
+			for path in &inputs {
+				let format = match format {
+					Some(format) => format,
+					None => Format::detect(path)?,
+				};
+				let count = input::add_file(&mut builder, path, format, &csv_options)
+					.map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+				eprintln!("Read {} items from {}", count, path.display());
+			}
+
+			let index = builder.finish()?;
+			let bytes = index.to_bytes()?;
+			std::fs::write(&output, &bytes)?;
+			eprintln!(
+				"Wrote {} items, {} keywords, {} bytes to {}",
+				index.len(),
+				index.keyword_count(),
+				bytes.len(),
+				output.display()
+			);
+			Ok(true)
+		}
+		Command::Search {
+			index,
+			query,
+			max_results,
+			offset,
+			max_expansions,
+			min_fuzzy_length,
+			min_two_typo_length,
+			no_transpositions,
+			phonetic_weight,
+			no_patterns,
+			filters,
+			facets,
+			ranges,
+			sort,
+			boost_mode,
+			field_boosts,
+			collapse,
+			stats,
+		} => {
+			let index = load_index(&index, true)?;
+			let mut facet_filters: Vec<FacetFilter> = Vec::new();
+			for (facet, value) in filters {
+				match facet_filters
+					.iter_mut()
+					.find(|filter| filter.facet == facet)
+				{
+					Some(filter) => filter.values.push(value),
+					None => facet_filters.push(FacetFilter {
+						facet,
+						values: vec![value],
+					}),
+				}
+			}
+			let options = SearchOptions {
+				max_results,
+				offset,
+				max_expansions,
+				min_fuzzy_length,
+				min_two_typo_length,
+				transpositions: !no_transpositions,
+				phonetic_weight,
+				patterns: !no_patterns,
+				filters: facet_filters,
+				facets,
+				ranges,
+				sort,
+				boost: boost_mode,
+				field_boosts: field_boosts.into_iter().collect(),
+				collapse,
+			};
+			let results = docfind_core::search_with_options(&index, &query, &options)?;
+			for hit in &results.hits {
+				if collapse {
+					println!("{}\t{}\t+{}", hit.score, hit.id, hit.collapsed);
+				} else {
+					println!("{}\t{}", hit.score, hit.id);
+				}
+			}
+			if let Some(correction) = &results.correction {
+				eprintln!("Did you mean: {}", correction);
+			}
+			for facet in &results.facets {
+				eprintln!("{}:", facet.facet);
+				for value in &facet.values {
+					eprintln!("  {:>8}  {}", value.count, value.value);
+				}
+			}
+			if stats {
+				let stats = &results.stats;
+				eprintln!(
+					"{} matching items; {} query words matched {} keywords, {} expanded",
+					results.total, stats.query_words, stats.matched_keywords, stats.expanded_keywords
+				);
+			}
+			Ok(true)
+		}
+		Command::Suggest {
+			index,
+			query,
+			max_suggestions,
+			rank,
+		} => {
+			let index = load_index(&index, true)?;
+			let options = SuggestOptions {
+				max_suggestions,
+				rank,
+			};
+			for suggestion in docfind_core::suggest_with_options(&index, &query, &options)? {
+				println!(
+					"{}\t{}\t{}",
+					suggestion.frequency, suggestion.weight, suggestion.text
+				);
+			}
+			Ok(true)
+		}
+		Command::Inspect {
+			index,
+			top,
+			json,
+			dump,
+		} => {
+			let index = load_index(&index, false)?;
+
+			if dump {
+				for entry in index.dump()? {
+					let postings: Vec<String> = entry
+						.postings
+						.iter()
+						.map(|(id, weight)| format!("{}:{}", id, weight))
+						.collect();
+					println!("{}\t{}", entry.keyword, postings.join(" "));
+				}
+				return Ok(true);
+			}
+
+			let stats = index.stats(top)?;
+			if json {
+				println!("{}", serde_json::to_string_pretty(&stats)?);
+				return Ok(true);
+			}
+
+			println!("items:          {}", stats.items);
+			println!("keywords:       {}", stats.keywords);
+			println!("postings:       {}", stats.postings);
+			println!("id dictionary:  {} symbols", stats.id_dictionary_symbols);
+			println!("content hash:   {:016x}", index.content_hash());
+			println!();
+			println!("bytes by section:");
+			let sections = &stats.sections;
+			for (name, size) in [
+				("fst", sections.fst),
+				("id dictionary", sections.id_dictionary),
+				("ids", sections.ids),
+				("postings", sections.postings),
+				("keyword weights", sections.keyword_weights),
+				("phonetic", sections.phonetic),
+				("fields", sections.fields),
+				("attributes", sections.attributes),
+				("total", sections.total),
+			] {
+				println!("  {:<14}{:>12}", name, size);
+			}
+			println!();
+			println!("keywords by posting count:");
+			for bucket in &stats.posting_histogram {
+				println!(
+					"  {:>8}..={:<8}{:>10}",
+					bucket.min, bucket.max, bucket.keywords
+				);
+			}
+			println!();
+			println!("most frequent keywords:");
+			for keyword in &stats.top_keywords {
+				println!(
+					"  {:<24}{:>8} items{:>10} total weight",
+					keyword.keyword, keyword.postings, keyword.total_weight
+				);
+			}
+			Ok(true)
+		}
+		Command::Verify {
+			index,
+			expectations,
+			max_results,
+		} => {
+			let index = load_index(&index, false)?;
+			let mut ok = true;
+
+			if let Err(e) = index.verify() {
+				println!("FAIL {}", e);
+				return Ok(false);
+			}
+
+			for (query, id) in expectations {
+				let results = docfind_core::search(&index, &query, max_results)?;
+				match results.iter().position(|result| *result == id) {
+					Some(rank) => println!("ok   '{}' -> {} (rank {})", query, id, rank + 1),
+					None => {
+						println!("FAIL '{}' -> {} not in the top {}", query, id, max_results);
+						ok = false;
+					}
+				}
+			}
+
+			if ok {
+				println!("ok   index with {} items is valid", index.len());
+			}
+			Ok(ok)
+		}
+	}
+}
+
+fn main() -> ExitCode {
+	match run(Cli::parse().command) {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(e) => {
+			eprintln!("error: {}", e);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use docfind_core::{InputItem, SearchTokens, build_index};
+	use std::path::Path;
+
+	/// Writes an index of two items to a file unique to this process and
+	/// `name`, returning its path.
+	fn write_index(name: &str) -> PathBuf {
+		let item = |id: &str, raw: &str| InputItem {
+			id: id.to_string(),
+			search_terms: vec![(SearchTokens::Raw(raw.to_string()), 100)],
+			..Default::default()
+		};
+		let index = build_index(vec![item("a", "rust guide"), item("b", "python guide")]).unwrap();
+		let path =
+			std::env::temp_dir().join(format!("docfind-cli-{}-{}.bin", name, std::process::id()));
+		std::fs::write(&path, index.to_bytes().unwrap()).unwrap();
+		path
+	}
+
+	fn verify(index: &Path, expectations: &[(&str, &str)]) -> bool {
+		run(Command::Verify {
+			index: index.to_path_buf(),
+			expectations: expectations
+				.iter()
+				.map(|(query, id)| (query.to_string(), id.to_string()))
+				.collect(),
+			max_results: 1,
+		})
+		.unwrap()
+	}
+
+	#[test]
+	fn test_verify_exit_status() {
+		let path = write_index("verify");
+		assert!(verify(&path, &[]));
+		assert!(verify(&path, &[("rust", "a"), ("python", "b")]));
+		assert!(!verify(&path, &[("rust", "a"), ("python", "a")]));
+		assert!(!verify(&path, &[("rust", "missing")]));
+
+		let mut bytes = std::fs::read(&path).unwrap();
+		let last = bytes.len() - 1;
+		bytes[last] ^= 1;
+		std::fs::write(&path, &bytes).unwrap();
+		assert!(!verify(&path, &[]));
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn test_search_rejects_damaged_index() {
+		let path = write_index("search");
+		let mut bytes = std::fs::read(&path).unwrap();
+		let last = bytes.len() - 1;
+		bytes[last] ^= 1;
+		std::fs::write(&path, &bytes).unwrap();
+
+		assert!(load_index(&path, false).is_ok());
+		assert!(load_index(&path, true).is_err());
+		std::fs::remove_file(&path).unwrap();
+	}
+}
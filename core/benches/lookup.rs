@@ -0,0 +1,33 @@
+//! Per-query latency on the `size.json` demo corpus, and the cost of looking
+//! up item IDs, which `dump` does for every posting.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use docfind_core::{Index, InputItem, build_index, search};
+
+fn size_index() -> Index {
+	let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../demo/build_index/size.json");
+	let json = std::fs::read_to_string(path).expect("size.json corpus");
+	let items: Vec<InputItem> = serde_json::from_str(&json).expect("valid size.json");
+	let bytes = build_index(items).unwrap().to_bytes().unwrap();
+	Index::from_bytes(&bytes).unwrap()
+}
+
+fn lookup(c: &mut Criterion) {
+	let index = size_index();
+	let mut group = c.benchmark_group("size_json");
+
+	for max_results in [10, 100] {
+		for query in ["bike", "6999cdb2", "soft bik"] {
+			group.bench_with_input(
+				BenchmarkId::new(format!("search_{}", max_results), query),
+				&query,
+				|b, query| b.iter(|| search(&index, query, max_results).unwrap()),
+			);
+		}
+	}
+	group.bench_function("dump", |b| b.iter(|| index.dump().unwrap()));
+	group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+//! Build, load and search latency over synthetic corpora of several sizes.
+//!
+//! Save a baseline with `cargo bench --bench search -- --save-baseline main`
+//! and compare a change against it with `--baseline main`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use docfind_core::{Index, InputItem, SearchTokens, build_index, search};
+
+const CORPUS_SIZES: [usize; 3] = [1_000, 10_000, 50_000];
+const VOCABULARY_SIZE: usize = 20_000;
+const SYLLABLES: [&str; 16] = [
+	"ka", "lo", "mi", "ne", "ru", "sa", "ti", "vo", "bel", "dor", "fen", "gar", "hul", "pin", "ster",
+	"wex",
+];
+
+/// Deterministic xorshift generator, so every run benchmarks the same corpus.
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	/// Skewed towards small values, so low word ranks are common like in text.
+	fn skewed(&mut self, bound: usize) -> usize {
+		let uniform = (self.next() % 1_000_000) as f64 / 1_000_000.0;
+		(uniform * uniform * uniform * bound as f64) as usize
+	}
+}
+
+/// The word with frequency rank `rank`, spelled from syllables.
+fn word(rank: usize) -> String {
+	let mut word = String::new();
+	let mut n = rank;
+	loop {
+		word.push_str(SYLLABLES[n % SYLLABLES.len()]);
+		n /= SYLLABLES.len();
+		if n == 0 {
+			break;
+		}
+	}
+	word
+}
+
+fn corpus(size: usize) -> Vec<InputItem> {
+	let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+	(0..size)
+		.map(|i| {
+			let mut words = |count: usize| {
+				(0..count)
+					.map(|_| word(rng.skewed(VOCABULARY_SIZE)))
+					.collect::<Vec<_>>()
+			};
+			let title = words(4).join(" ");
+			let body = words(24);
+
+			InputItem {
+				id: format!("doc-{:06}", i),
+				search_terms: vec![
+					(SearchTokens::Raw(title), 100),
+					(SearchTokens::Tokens(body), 40),
+				],
+				..Default::default()
+			}
+		})
+		.collect()
+}
+
+fn build(c: &mut Criterion) {
+	let mut group = c.benchmark_group("build_index");
+	group.sample_size(10);
+
+	for size in CORPUS_SIZES {
+		let items = corpus(size);
+		group.throughput(Throughput::Elements(size as u64));
+		group.bench_with_input(BenchmarkId::from_parameter(size), &items, |b, items| {
+			b.iter(|| build_index(items.clone()).unwrap())
+		});
+	}
+	group.finish();
+}
+
+fn from_bytes(c: &mut Criterion) {
+	let mut group = c.benchmark_group("from_bytes");
+
+	for size in CORPUS_SIZES {
+		let bytes = build_index(corpus(size)).unwrap().to_bytes().unwrap();
+		group.throughput(Throughput::Bytes(bytes.len() as u64));
+		group.bench_with_input(BenchmarkId::from_parameter(size), &bytes, |b, bytes| {
+			b.iter(|| Index::from_bytes(bytes).unwrap())
+		});
+	}
+	group.finish();
+}
+
+fn queries() -> Vec<(&'static str, String, usize)> {
+	let long_query: Vec<String> = (0..8).map(|rank| word(rank * 7 + 3)).collect();
+	let mut typo = word(300);
+	typo.insert(2, 'e');
+
+	vec![
+		("short_prefix", word(1)[..1].to_string(), 10),
+		("prefix", word(40)[..3].to_string(), 10),
+		("exact", word(40), 10),
+		// One of the rarest words, matching a handful of items whatever the
+		// corpus size, so its latency should not grow with the corpus.
+		("rare", word(VOCABULARY_SIZE - 1), 10),
+		("typo", typo, 10),
+		("multi_word", format!("{} {}", word(12), word(250)), 10),
+		("long_multi_word", long_query.join(" "), 10),
+		("max_results_1000", word(3), 1000),
+	]
+}
+
+fn search_queries(c: &mut Criterion) {
+	for size in CORPUS_SIZES {
+		let index = build_index(corpus(size)).unwrap();
+		let mut group = c.benchmark_group(format!("search/{}", size));
+
+		for (name, query, max_results) in queries() {
+			group.bench_with_input(BenchmarkId::new(name, &query), &query, |b, query| {
+				b.iter(|| search(&index, query, max_results).unwrap())
+			});
+		}
+		group.finish();
+	}
+}
+
+criterion_group!(benches, build, from_bytes, search_queries);
+criterion_main!(benches);
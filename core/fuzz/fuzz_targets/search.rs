@@ -0,0 +1,46 @@
+#![no_main]
+
+use docfind_core::{
+	FacetFilter, Index, RangeFilter, SearchOptions, SortKey, search_hits, search_with_options,
+};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte gives the query length, the query follows, and the rest is
+// read as an index. The query is also searched with every kind of option,
+// using its first word as the facet and attribute name.
+fuzz_target!(|data: &[u8]| {
+	let Some((&query_len, rest)) = data.split_first() else {
+		return;
+	};
+	let (query, bytes) = rest.split_at((query_len as usize).min(rest.len()));
+	let query = String::from_utf8_lossy(query);
+
+	if let Ok(index) = Index::from_bytes(bytes) {
+		let _ = search_hits(&index, &query, 10);
+
+		let name = query
+			.split_whitespace()
+			.next()
+			.unwrap_or_default()
+			.to_string();
+		let options = SearchOptions {
+			filters: vec![FacetFilter {
+				facet: name.clone(),
+				values: query.split_whitespace().map(str::to_string).collect(),
+			}],
+			facets: vec![name.clone()],
+			ranges: vec![RangeFilter {
+				attribute: name.clone(),
+				min: Some(0.0),
+				max: None,
+			}],
+			sort: vec![SortKey {
+				attribute: name,
+				descending: true,
+			}],
+			collapse: true,
+			..Default::default()
+		};
+		let _ = search_with_options(&index, &query, &options);
+	}
+});
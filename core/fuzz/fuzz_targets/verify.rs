@@ -0,0 +1,14 @@
+#![no_main]
+
+use docfind_core::{Index, search_hits};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| {
+	if let Ok(index) = Index::from_bytes(bytes) {
+		let _ = index.stats(10);
+		let _ = index.dump();
+		if index.verify().is_ok() {
+			search_hits(&index, "a", 10).expect("verified indexes can be searched");
+		}
+	}
+});
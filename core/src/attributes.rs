@@ -0,0 +1,310 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::InputItem;
+
+/// Per-item attributes, stored in columns next to the keyword postings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Attributes {
+	facets: Vec<FacetColumn>,
+	numbers: Vec<NumberColumn>,
+	/// The boost of each item, or nothing if no item has one.
+	boosts: Vec<Option<f32>>,
+	/// The largest boost, or `0` if no item has one.
+	max_boost: f32,
+	/// Group keys in sorted order.
+	groups: Vec<String>,
+	/// The group of each item, or nothing if no item has one.
+	item_groups: Vec<Option<u32>>,
+}
+
+/// The items having each value of one facet.
+///
+/// Items are kept as sorted index lists rather than bitsets so that facets
+/// with many rare values stay small; filters turn them into an [`ItemSet`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FacetColumn {
+	pub name: String,
+	/// Distinct values in sorted order.
+	pub values: Vec<String>,
+	/// For each value, the indices of the items having it.
+	pub items: Vec<Vec<u32>>,
+}
+
+/// The value of one numeric attribute for every item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NumberColumn {
+	pub name: String,
+	/// For each item, its value, or `None` if the item does not have one.
+	pub values: Vec<Option<f64>>,
+}
+
+/// The attributes of a single item, as given in its [`InputItem`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ItemAttributes {
+	pub facets: BTreeMap<String, Vec<String>>,
+	pub numbers: BTreeMap<String, f64>,
+	pub boost: Option<f32>,
+	pub group: Option<String>,
+}
+
+impl From<&InputItem> for ItemAttributes {
+	fn from(item: &InputItem) -> Self {
+		ItemAttributes {
+			facets: item.facets.clone(),
+			numbers: item.numbers.clone(),
+			boost: item.boost,
+			group: item.group.clone(),
+		}
+	}
+}
+
+impl Attributes {
+	pub fn facet(&self, name: &str) -> Option<&FacetColumn> {
+		self.facets.iter().find(|facet| facet.name == name)
+	}
+
+	pub fn number(&self, name: &str) -> Option<&NumberColumn> {
+		self.numbers.iter().find(|column| column.name == name)
+	}
+
+	/// The boost of each item, if any item has one.
+	pub fn boosts(&self) -> Option<&[Option<f32>]> {
+		(!self.boosts.is_empty()).then_some(self.boosts.as_slice())
+	}
+
+	/// The largest boost, which bounds how far boosts can lift a score.
+	pub fn max_boost(&self) -> f32 {
+		self.max_boost
+	}
+
+	/// The group of the item at `item_index`, as an index into the sorted
+	/// group keys.
+	pub fn group(&self, item_index: usize) -> Option<u32> {
+		self.item_groups.get(item_index).copied().flatten()
+	}
+
+	/// Decodes the attributes of each of the `len` items.
+	pub fn items(&self, len: usize) -> Vec<ItemAttributes> {
+		let mut items = vec![ItemAttributes::default(); len];
+		for facet in &self.facets {
+			for (value, value_items) in facet.values.iter().zip(&facet.items) {
+				for &item_index in value_items {
+					if let Some(item) = items.get_mut(item_index as usize) {
+						item
+							.facets
+							.entry(facet.name.clone())
+							.or_default()
+							.push(value.clone());
+					}
+				}
+			}
+		}
+		for column in &self.numbers {
+			for (item, value) in items.iter_mut().zip(&column.values) {
+				if let Some(value) = value {
+					item.numbers.insert(column.name.clone(), *value);
+				}
+			}
+		}
+		for (item, boost) in items.iter_mut().zip(&self.boosts) {
+			item.boost = *boost;
+		}
+		for (item, group) in items.iter_mut().zip(&self.item_groups) {
+			item.group = group.and_then(|group| self.groups.get(group as usize).cloned());
+		}
+		items
+	}
+
+	/// Checks that every column refers only to the first `len` items.
+	pub fn verify(&self, len: usize) -> Result<(), Box<dyn std::error::Error>> {
+		for facet in &self.facets {
+			if facet.values.len() != facet.items.len() {
+				return Err(format!("Facet '{}' has mismatched value lists", facet.name).into());
+			}
+			if facet
+				.items
+				.iter()
+				.flatten()
+				.any(|&item_index| item_index as usize >= len)
+			{
+				return Err(format!("Facet '{}' refers to a missing item", facet.name).into());
+			}
+		}
+		for column in &self.numbers {
+			if column.values.len() != len {
+				return Err(format!("Attribute '{}' does not cover every item", column.name).into());
+			}
+		}
+		if !self.boosts.is_empty() && self.boosts.len() != len {
+			return Err("Boosts do not cover every item".into());
+		}
+		if self
+			.boosts
+			.iter()
+			.flatten()
+			.any(|boost| !boost.is_finite() || *boost < 0.0)
+		{
+			return Err("Boosts must be finite and not negative".into());
+		}
+		if self.max_boost != largest_boost(&self.boosts) {
+			return Err("Largest boost does not match the boosts".into());
+		}
+		if !self.item_groups.is_empty() && self.item_groups.len() != len {
+			return Err("Groups do not cover every item".into());
+		}
+		if self
+			.item_groups
+			.iter()
+			.flatten()
+			.any(|&group| group as usize >= self.groups.len())
+		{
+			return Err("An item refers to a missing group".into());
+		}
+		Ok(())
+	}
+}
+
+fn largest_boost(boosts: &[Option<f32>]) -> f32 {
+	boosts
+		.iter()
+		.flatten()
+		.fold(0.0, |max, &boost| max.max(boost))
+}
+
+/// Collects the attributes of items added in index order.
+#[derive(Default)]
+pub(crate) struct AttributesBuilder {
+	len: u32,
+	facets: BTreeMap<String, BTreeMap<String, Vec<u32>>>,
+	numbers: BTreeMap<String, Vec<Option<f64>>>,
+	boosts: Vec<Option<f32>>,
+	groups: BTreeMap<String, Vec<u32>>,
+}
+
+impl AttributesBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds the attributes of the next item. Numbers that are not finite and
+	/// boosts that are negative or not finite are dropped, so that they can
+	/// always be compared.
+	pub fn push(&mut self, attributes: ItemAttributes) {
+		let item_index = self.len;
+		self.len += 1;
+
+		for (name, values) in attributes.facets {
+			let facet = self.facets.entry(name).or_default();
+			for value in values {
+				let items = facet.entry(value).or_default();
+				if items.last() != Some(&item_index) {
+					items.push(item_index);
+				}
+			}
+		}
+
+		for (name, value) in attributes.numbers {
+			if value.is_finite() {
+				let values = self.numbers.entry(name).or_default();
+				values.resize(item_index as usize, None);
+				values.push(Some(value));
+			}
+		}
+
+		if let Some(boost) = attributes.boost
+			&& boost.is_finite()
+			&& boost >= 0.0
+		{
+			self.boosts.resize(item_index as usize, None);
+			self.boosts.push(Some(boost));
+		}
+
+		if let Some(group) = attributes.group {
+			self.groups.entry(group).or_default().push(item_index);
+		}
+	}
+
+	pub fn finish(self) -> Attributes {
+		let facets = self
+			.facets
+			.into_iter()
+			.map(|(name, values)| {
+				let (values, items) = values.into_iter().unzip();
+				FacetColumn {
+					name,
+					values,
+					items,
+				}
+			})
+			.collect();
+		let numbers = self
+			.numbers
+			.into_iter()
+			.map(|(name, mut values)| {
+				values.resize(self.len as usize, None);
+				NumberColumn { name, values }
+			})
+			.collect();
+
+		let mut boosts = self.boosts;
+		if !boosts.is_empty() {
+			boosts.resize(self.len as usize, None);
+		}
+
+		let mut item_groups = Vec::new();
+		if !self.groups.is_empty() {
+			item_groups.resize(self.len as usize, None);
+		}
+		let mut groups = Vec::with_capacity(self.groups.len());
+		for (group, items) in self.groups {
+			for item_index in items {
+				item_groups[item_index as usize] = Some(groups.len() as u32);
+			}
+			groups.push(group);
+		}
+
+		Attributes {
+			facets,
+			numbers,
+			max_boost: largest_boost(&boosts),
+			boosts,
+			groups,
+			item_groups,
+		}
+	}
+}
+
+/// A set of item indices, used to filter and count search results. Sets
+/// only hold the items added, so small ones stay cheap in large indexes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ItemSet {
+	items: HashSet<usize>,
+}
+
+impl ItemSet {
+	pub fn new() -> Self {
+		ItemSet::default()
+	}
+
+	pub fn insert(&mut self, item_index: usize) {
+		self.items.insert(item_index);
+	}
+
+	pub fn contains(&self, item_index: usize) -> bool {
+		self.items.contains(&item_index)
+	}
+
+	pub fn intersect_with(&mut self, other: &ItemSet) {
+		self.items.retain(|item_index| other.contains(*item_index));
+	}
+}
+
+impl FromIterator<usize> for ItemSet {
+	fn from_iter<I: IntoIterator<Item = usize>>(items: I) -> Self {
+		ItemSet {
+			items: items.into_iter().collect(),
+		}
+	}
+}
@@ -0,0 +1,82 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use fst::{IntoStreamer, Streamer};
+
+use crate::pattern::Pattern;
+use crate::search::split_boost;
+use crate::typo::EditDistance;
+use crate::{Index, SearchOptions};
+
+/// Largest number of edits a correction may make to a query word.
+const MAX_DISTANCE: u32 = 2;
+
+/// Proposes a corrected `query`, or nothing if no word needs correcting.
+///
+/// Words that are keywords of any of `indexes`, patterns, or that have fewer
+/// than [`SearchOptions::min_fuzzy_length`] characters, are kept. Every other word
+/// is replaced by the keyword within [`MAX_DISTANCE`] edits that needs the
+/// fewest edits, then occurs in the most items across `indexes`. Edits are
+/// counted as they are for searching. Multipliers such as `^2` are kept.
+pub(crate) fn correct_query(
+	indexes: &[&Index],
+	query: &str,
+	options: &SearchOptions,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+	let maps = indexes
+		.iter()
+		.map(|index| fst::Map::new(index.fst.as_slice()))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut corrected = String::with_capacity(query.len());
+	let mut changed = false;
+	for piece in query.split_inclusive(char::is_whitespace) {
+		let (term, _) = split_boost(piece.trim_end());
+		match correct_word(indexes, &maps, term, options)? {
+			Some(keyword) => {
+				corrected.push_str(&keyword);
+				corrected.push_str(&piece[term.len()..]);
+				changed = true;
+			}
+			None => corrected.push_str(piece),
+		}
+	}
+	Ok(changed.then_some(corrected))
+}
+
+fn correct_word(
+	indexes: &[&Index],
+	maps: &[fst::Map<&[u8]>],
+	term: &str,
+	options: &SearchOptions,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+	let word = term.to_lowercase();
+	if word.chars().count() < options.min_fuzzy_length
+		|| maps.iter().any(|map| map.contains_key(&word))
+		|| (options.patterns && Pattern::parse(term).is_some())
+	{
+		return Ok(None);
+	}
+	let automaton = EditDistance::new(&word, MAX_DISTANCE, options.transpositions);
+
+	let mut frequencies: BTreeMap<String, usize> = BTreeMap::new();
+	for (index, map) in indexes.iter().zip(maps) {
+		let mut matches = map.search(&automaton).into_stream();
+		while let Some((keyword, keyword_index)) = matches.next() {
+			let frequency = index
+				.keyword_to_items
+				.get(keyword_index as usize)
+				.map_or(0, Vec::len);
+			*frequencies
+				.entry(String::from_utf8(keyword.to_vec())?)
+				.or_default() += frequency;
+		}
+	}
+
+	Ok(
+		frequencies
+			.into_iter()
+			.min_by_key(|(keyword, frequency)| (automaton.distance(keyword), Reverse(*frequency)))
+			.map(|(keyword, _)| keyword),
+	)
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Postings;
+
+/// The field each posting's term came from, so that fields can be boosted at
+/// query time.
+///
+/// Field `0` stands for terms given without a field and `n` for `names[n - 1]`.
+/// Only keywords with at least one posting from a field store anything, so
+/// indexes without fields pay nothing for them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Fields {
+	/// Field names in sorted order.
+	names: Vec<String>,
+	/// For each keyword, the field of each of its postings, or nothing if
+	/// none of them has a field. Keywords past the end have no fields.
+	postings: Vec<Vec<u8>>,
+}
+
+impl Fields {
+	pub fn new(names: Vec<String>, postings: Vec<Vec<u8>>) -> Self {
+		Fields { names, postings }
+	}
+
+	pub fn names(&self) -> &[String] {
+		&self.names
+	}
+
+	/// The fields of the postings of `keyword_index`, empty if none has one.
+	pub fn posting_fields(&self, keyword_index: usize) -> &[u8] {
+		self.postings.get(keyword_index).map_or(&[], Vec::as_slice)
+	}
+
+	/// Checks that the fields line up with `keyword_to_items`.
+	pub fn verify(&self, keyword_to_items: &[Postings]) -> Result<(), Box<dyn std::error::Error>> {
+		if self.postings.len() > keyword_to_items.len() {
+			return Err("Fields refer to missing posting lists".into());
+		}
+		for (keyword_index, fields) in self.postings.iter().enumerate() {
+			if !fields.is_empty() && fields.len() != keyword_to_items[keyword_index].len() {
+				return Err(format!("Posting list {} has mismatched fields", keyword_index).into());
+			}
+			if fields
+				.iter()
+				.any(|&field| field as usize > self.names.len())
+			{
+				return Err(format!("Posting list {} refers to a missing field", keyword_index).into());
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Numbers field names while an index is built.
+///
+/// Names are numbered in the order they are first seen, then renumbered in
+/// sorted order by [`FieldIds::finish`], so the result does not depend on
+/// the order of the items.
+#[derive(Default)]
+pub(crate) struct FieldIds {
+	names: Vec<String>,
+	ids: HashMap<String, u8>,
+}
+
+impl FieldIds {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The provisional number of `name`, or `0` for terms without a field.
+	pub fn id(&mut self, name: Option<&str>) -> Result<u8, Box<dyn std::error::Error>> {
+		let Some(name) = name else {
+			return Ok(0);
+		};
+		if let Some(&id) = self.ids.get(name) {
+			return Ok(id);
+		}
+		if self.names.len() >= u8::MAX as usize {
+			return Err(format!("Too many fields, at most {} are supported", u8::MAX).into());
+		}
+
+		self.names.push(name.to_string());
+		let id = self.names.len() as u8;
+		self.ids.insert(name.to_string(), id);
+		Ok(id)
+	}
+
+	/// Returns the sorted field names and, for each provisional number, the
+	/// final one.
+	pub fn finish(self) -> (Vec<String>, Vec<u8>) {
+		let mut sorted: Vec<(String, usize)> = self
+			.names
+			.into_iter()
+			.enumerate()
+			.map(|(position, name)| (name, position + 1))
+			.collect();
+		sorted.sort();
+
+		let mut remap = vec![0; sorted.len() + 1];
+		for (position, (_, provisional)) in sorted.iter().enumerate() {
+			remap[*provisional] = position as u8 + 1;
+		}
+		(sorted.into_iter().map(|(name, _)| name).collect(), remap)
+	}
+}
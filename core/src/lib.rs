@@ -1,19 +1,92 @@
 use serde::{Deserialize, Serialize};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
+
+mod attributes;
+mod correct;
+mod fields;
+mod merge;
+mod multi;
+mod pattern;
+mod phonetic;
+mod score;
+mod search;
+mod stats;
+mod stream;
+mod suggest;
+mod typo;
+mod verify;
+mod writer;
+
+use attributes::{Attributes, AttributesBuilder, ItemAttributes};
+use fields::{FieldIds, Fields};
+use phonetic::PhoneticIndex;
+
+pub use merge::DuplicatePolicy;
+pub use multi::{MultiIndex, MultiSearchHit, MultiSearchResults};
+pub use search::{
+	BoostMode, FacetCounts, FacetFilter, FacetValueCount, RangeFilter, SearchHit, SearchOptions,
+	SearchResults, SearchStats, SortKey, search, search_hits, search_with_options,
+};
+pub use stats::{HistogramBucket, IndexStats, KeywordPostings, KeywordStats, SectionSizes};
+pub use stream::StreamingIndexBuilder;
+pub use suggest::{SuggestOptions, SuggestRank, Suggestion, suggest, suggest_with_options};
+pub use writer::IndexWriter;
 
 /// A minimal FSST-compressed vector of UTF-8 strings with random access.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "FsstParts")]
 pub struct FsstStrVec {
 	dict_syms: Vec<[u8; 8]>,
 	dict_lens: Vec<u8>,
 	offsets: Vec<u32>,
 	data: Vec<u8>,
+	/// `dict_syms` decoded once, so lookups don't rebuild the symbol table.
+	#[serde(skip)]
+	symbols: Vec<fsst::Symbol>,
+}
+
+/// The serialized fields of an [`FsstStrVec`].
+#[derive(Deserialize)]
+struct FsstParts {
+	dict_syms: Vec<[u8; 8]>,
+	dict_lens: Vec<u8>,
+	offsets: Vec<u32>,
+	data: Vec<u8>,
 }
 
+impl TryFrom<FsstParts> for FsstStrVec {
+	type Error = Box<dyn std::error::Error>;
+
+	fn try_from(parts: FsstParts) -> Result<Self, Self::Error> {
+		let strings = FsstStrVec::from_parts(parts);
+		strings.check_dictionary()?;
+		Ok(strings)
+	}
+}
+
+impl std::fmt::Debug for FsstStrVec {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("FsstStrVec")
+			.field("dict_syms", &self.dict_syms)
+			.field("dict_lens", &self.dict_lens)
+			.field("offsets", &self.offsets)
+			.field("data", &self.data)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Upper bound on the bytes used to train the FSST dictionary.
+///
+/// `fsst::Compressor::train` draws its own sample from larger inputs using
+/// `usize` arithmetic, which picks different strings on 32-bit targets such as
+/// wasm32. Staying below its sampling threshold keeps the dictionary identical
+/// across platforms.
+const FSST_TRAINING_BYTES: usize = (1 << 14) - 1;
+
 impl FsstStrVec {
 	fn from_strings(strings: &[impl AsRef<str>]) -> Self {
-		let sample: Vec<&[u8]> = strings.iter().map(|s| s.as_ref().as_bytes()).collect();
+		let sample = Self::training_sample(strings);
 		let compressor = fsst::Compressor::train(&sample);
 
 		let syms: Vec<fsst::Symbol> = compressor.symbol_table().to_vec();
@@ -32,48 +105,133 @@ impl FsstStrVec {
 			.map(|sym| u64::to_le_bytes(sym.to_u64()))
 			.collect();
 
-		Self {
+		Self::from_parts(FsstParts {
 			dict_syms,
 			dict_lens: lens,
 			offsets,
 			data,
+		})
+	}
+
+	fn from_parts(parts: FsstParts) -> Self {
+		let symbols = parts
+			.dict_syms
+			.iter()
+			.map(fsst::Symbol::from_slice)
+			.collect();
+
+		Self {
+			dict_syms: parts.dict_syms,
+			dict_lens: parts.dict_lens,
+			offsets: parts.offsets,
+			data: parts.data,
+			symbols,
+		}
+	}
+
+	/// Picks evenly spaced strings until the training budget is used up.
+	fn training_sample(strings: &[impl AsRef<str>]) -> Vec<&[u8]> {
+		let total: usize = strings.iter().map(|s| s.as_ref().len()).sum();
+		let stride = total.div_ceil(FSST_TRAINING_BYTES).max(1);
+
+		let mut sample = Vec::new();
+		let mut remaining = FSST_TRAINING_BYTES;
+		for s in strings.iter().step_by(stride) {
+			let bytes = s.as_ref().as_bytes();
+			let bytes = &bytes[..bytes.len().min(remaining)];
+			if bytes.is_empty() {
+				continue;
+			}
+			remaining -= bytes.len();
+			sample.push(bytes);
 		}
+		sample
 	}
 
 	pub fn len(&self) -> usize {
 		self.offsets.len()
 	}
 
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
+	}
+
+	/// Returns the string at `i`, or `None` if it is out of bounds or corrupted.
 	pub fn get(&self, i: usize) -> Option<String> {
-		if i >= self.len() {
-			return None;
-		}
-		let start = self.offsets[i] as usize;
-		let end = if i + 1 < self.len() {
-			self.offsets[i + 1] as usize
-		} else {
-			self.data.len()
-		};
-		let codes = &self.data[start..end];
+		self.try_get(i).ok().flatten()
+	}
 
-		let syms: Vec<fsst::Symbol> = self
-			.dict_syms
-			.iter()
-			.map(fsst::Symbol::from_slice)
-			.collect();
-		let decomp = fsst::Decompressor::new(&syms, &self.dict_lens);
+	/// Like [`FsstStrVec::get`], but reports corrupted data as an error.
+	pub fn try_get(&self, i: usize) -> Result<Option<String>, Box<dyn std::error::Error>> {
+		let Some(&start) = self.offsets.get(i) else {
+			return Ok(None);
+		};
+		let end = self
+			.offsets
+			.get(i + 1)
+			.map_or(self.data.len(), |&end| end as usize);
+		let codes = self
+			.data
+			.get(start as usize..end)
+			.ok_or("ID offset out of bounds")?;
+
+		if !verify::valid_fsst_codes(codes, self.symbols.len()) {
+			return Err("ID contains invalid FSST codes".into());
+		}
 
+		let decomp = fsst::Decompressor::new(&self.symbols, &self.dict_lens);
 		let bytes = decomp.decompress(codes);
-		Some(String::from_utf8(bytes).expect("FSST preserves UTF-8 for UTF-8 input"))
+		Ok(Some(String::from_utf8(bytes)?))
+	}
+
+	/// Returns the strings at `indices`, failing if any is out of bounds.
+	pub fn get_many(
+		&self,
+		indices: impl IntoIterator<Item = usize>,
+	) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+		indices
+			.into_iter()
+			.map(|i| {
+				self
+					.try_get(i)?
+					.ok_or_else(|| format!("String index {} out of bounds", i).into())
+			})
+			.collect()
+	}
+
+	/// Iterates over all strings in order.
+	pub fn iter(&self) -> impl Iterator<Item = Result<String, Box<dyn std::error::Error>>> + '_ {
+		(0..self.len()).map(|i| Ok(self.try_get(i)?.ok_or("Failed to get string")?))
 	}
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InputItem {
 	pub id: String,
 	#[serde(deserialize_with = "parse_search_terms")]
 	pub search_terms: Vec<(SearchTokens, u8)>,
+	/// Further search terms by the field they come from, such as `"title"`,
+	/// so that fields can be boosted at query time with
+	/// [`SearchOptions::field_boosts`].
+	#[serde(default, deserialize_with = "parse_field_terms")]
+	pub fields: BTreeMap<String, Vec<(SearchTokens, u8)>>,
+	/// Facet values by facet name, such as `"category": "books"`. In JSON a
+	/// facet with several values is given as an array.
+	#[serde(default, deserialize_with = "parse_facets")]
+	pub facets: BTreeMap<String, Vec<String>>,
+	/// Numeric attributes by name, such as `"price": 9.5`, for range filters
+	/// and sorting.
+	#[serde(default)]
+	pub numbers: BTreeMap<String, f64>,
+	/// Query-independent ranking signal such as popularity, combined with the
+	/// query score as set by [`SearchOptions::boost`].
+	#[serde(default)]
+	pub boost: Option<f32>,
+	/// Key of the group the item belongs to, such as the URL of the page a
+	/// section is on, for collapsing results with [`SearchOptions::collapse`].
+	#[serde(default)]
+	pub group: Option<String>,
 }
 
 fn parse_search_terms<'de, D>(deserializer: D) -> Result<Vec<(SearchTokens, u8)>, D::Error>
@@ -109,6 +267,45 @@ where
 	Ok(result)
 }
 
+fn parse_field_terms<'de, D>(deserializer: D) -> Result<BTreeMap<String, SearchTerms>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	#[derive(Deserialize)]
+	struct FieldTerms(#[serde(deserialize_with = "parse_search_terms")] SearchTerms);
+
+	let fields: BTreeMap<String, FieldTerms> = serde::Deserialize::deserialize(deserializer)?;
+	Ok(
+		fields
+			.into_iter()
+			.map(|(name, terms)| (name, terms.0))
+			.collect(),
+	)
+}
+
+fn parse_facets<'de, D>(deserializer: D) -> Result<BTreeMap<String, Vec<String>>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum FacetValues {
+		One(String),
+		Many(Vec<String>),
+	}
+
+	let facets: BTreeMap<String, FacetValues> = serde::Deserialize::deserialize(deserializer)?;
+	Ok(
+		facets
+			.into_iter()
+			.map(|(name, values)| match values {
+				FacetValues::One(value) => (name, vec![value]),
+				FacetValues::Many(values) => (name, values),
+			})
+			.collect(),
+	)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "value")]
 pub enum SearchTokens {
@@ -116,157 +313,431 @@ pub enum SearchTokens {
 	Tokens(Vec<String>),
 }
 
+/// FNV-1a over the bytes postcard produces, without buffering them.
+struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+	fn default() -> Self {
+		ContentHasher(0xcbf2_9ce4_8422_2325)
+	}
+}
+
+impl postcard::ser_flavors::Flavor for ContentHasher {
+	type Output = u64;
+
+	fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+		self.0 = (self.0 ^ data as u64).wrapping_mul(0x0100_0000_01b3);
+		Ok(())
+	}
+
+	fn finalize(self) -> postcard::Result<u64> {
+		Ok(self.0)
+	}
+}
+
+/// Items containing a keyword as `(item index, weight)` pairs.
+type Postings = Vec<(usize, u8)>;
+
+/// Postings along with the field of each term, while an index is built.
+type FieldPostings = Vec<(usize, u8, u8)>;
+
+/// The FST, posting lists and posting fields of an index.
+type KeywordMap = (Vec<u8>, Vec<Postings>, Fields);
+
+type SearchTerms = Vec<(SearchTokens, u8)>;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Index {
 	fst: Vec<u8>,
 	ids: FsstStrVec,
 	keyword_to_items: Vec<Vec<(usize, u8)>>,
+	/// Summed weights of each posting list, for ranking suggestions.
+	keyword_weights: Vec<u64>,
+	phonetic: PhoneticIndex,
+	fields: Fields,
+	attributes: Attributes,
+	build_options: BuildOptions,
+	/// Hash of all preceding fields; must stay the last field.
+	content_hash: u64,
 }
 
 impl Index {
+	fn new(
+		fst: Vec<u8>,
+		ids: FsstStrVec,
+		keyword_to_items: Vec<Postings>,
+		fields: Fields,
+		attributes: Attributes,
+		build_options: BuildOptions,
+	) -> Result<Self, Box<dyn std::error::Error>> {
+		let phonetic = if build_options.phonetic {
+			PhoneticIndex::new(&fst)?
+		} else {
+			PhoneticIndex::default()
+		};
+		let mut index = Index {
+			fst,
+			ids,
+			keyword_weights: suggest::keyword_weights(&keyword_to_items),
+			keyword_to_items,
+			phonetic,
+			fields,
+			attributes,
+			build_options,
+			content_hash: 0,
+		};
+		index.content_hash = index.compute_content_hash();
+		Ok(index)
+	}
+
+	/// FNV-1a hash of the serialized index contents.
+	///
+	/// Identical input items always produce the same hash, on every platform.
+	pub fn content_hash(&self) -> u64 {
+		self.content_hash
+	}
+
+	/// Whether the stored content hash matches the index contents.
+	pub fn has_valid_content_hash(&self) -> bool {
+		self.content_hash == self.compute_content_hash()
+	}
+
+	fn compute_content_hash(&self) -> u64 {
+		let contents = (
+			&self.fst,
+			&self.ids,
+			&self.keyword_to_items,
+			&self.keyword_weights,
+			&self.phonetic,
+			&self.fields,
+			&self.attributes,
+			&self.build_options,
+		);
+		postcard::serialize_with_flavor(&contents, ContentHasher::default())
+			.expect("hashing never runs out of space")
+	}
+
+	/// Deserializes an index, rejecting it if the FST checksums do not match.
+	///
+	/// Use [`Index::from_bytes_verified`] to check the rest of the index too.
 	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
 		let index: Index = postcard::from_bytes(bytes)?;
+		index.verified_fst()?;
+		index.phonetic.check()?;
 		Ok(index)
 	}
 
 	pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
 		Ok(postcard::to_allocvec(self)?)
 	}
+
+	/// Number of items in the index.
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+
+	/// Number of distinct keywords in the index.
+	pub fn keyword_count(&self) -> usize {
+		self.keyword_to_items.len()
+	}
+
+	/// The options the index was built with, which [`IndexWriter`] also uses
+	/// for the items it adds.
+	pub fn build_options(&self) -> &BuildOptions {
+		&self.build_options
+	}
+}
+
+/// Settings for turning items into keywords.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BuildOptions {
+	pub keyword_merge: KeywordMerge,
+	/// Also index keywords by how they sound, so that searches can match
+	/// misspellings such as `jonson` for `johnson`.
+	pub phonetic: bool,
+}
+
+/// How the weights of a keyword occurring several times in one item are
+/// combined into the weight of its posting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeywordMerge {
+	/// The weight of the first occurrence.
+	#[default]
+	First,
+	/// The highest weight of any occurrence.
+	Max,
+	/// The sum of the weights of all occurrences, saturating at 255.
+	Sum,
+	/// The highest weight scaled by `1 + ln(count)`, so that repeats add
+	/// with diminishing returns, saturating at 255.
+	WeightedByCount,
 }
 
 pub fn build_index(items: Vec<InputItem>) -> Result<Index, Box<dyn std::error::Error>> {
+	build_index_with_options(items, BuildOptions::default())
+}
+
+pub fn build_index_with_options(
+	items: Vec<InputItem>,
+	options: BuildOptions,
+) -> Result<Index, Box<dyn std::error::Error>> {
 	let mut ids: Vec<String> = Vec::new();
-	let mut keywords_to_items: HashMap<String, Vec<(usize, u8)>> = HashMap::new();
+	let mut keywords_to_items: HashMap<String, FieldPostings> = HashMap::new();
+	let mut field_ids = FieldIds::new();
+	let mut attributes = AttributesBuilder::new();
 
 	for (item_index, item) in items.iter().enumerate() {
 		ids.push(item.id.clone());
-
-		let mut seen_keywords: HashSet<String> = HashSet::new();
-
-		for (tokens, weight) in &item.search_terms {
-			match tokens {
-				SearchTokens::Raw(raw) => {
-					for keyword in raw.split_whitespace() {
-						let keyword = keyword.to_lowercase();
-						if keyword.is_empty() || seen_keywords.contains(&keyword) {
-							continue;
-						}
-						seen_keywords.insert(keyword.clone());
-
-						keywords_to_items
-							.entry(keyword)
-							.or_default()
-							.push((item_index, *weight));
-					}
-				}
-				SearchTokens::Tokens(tokens) => {
-					for keyword in tokens {
-						let keyword = keyword.to_lowercase();
-						if keyword.is_empty() || seen_keywords.contains(&keyword) {
-							continue;
-						}
-						seen_keywords.insert(keyword.clone());
-
-						keywords_to_items
-							.entry(keyword)
-							.or_default()
-							.push((item_index, *weight));
-					}
-				}
-			}
+		attributes.push(ItemAttributes::from(item));
+
+		for (keyword, weight, field) in item_keywords(item, options.keyword_merge) {
+			let field = field_ids.id(field)?;
+			keywords_to_items
+				.entry(keyword)
+				.or_default()
+				.push((item_index, weight, field));
 		}
 	}
 
-	let mut fst_builder = fst::MapBuilder::memory();
-	let mut keyword_to_items: Vec<Vec<(usize, u8)>> = Vec::new();
-	let mut sorted_keywords: Vec<String> = keywords_to_items.keys().cloned().collect();
-	sorted_keywords.sort();
-
-	for (index, keyword) in sorted_keywords.iter().enumerate() {
-		fst_builder.insert(keyword, index as u64)?;
-
-		let mut item_scores = keywords_to_items.get(keyword).unwrap().clone();
-		item_scores.sort_by(|a, b| b.1.cmp(&a.1));
+	let mut sorted_keywords: Vec<(String, FieldPostings)> = keywords_to_items.into_iter().collect();
+	sorted_keywords.sort_by(|a, b| a.0.cmp(&b.0));
 
-		keyword_to_items.push(item_scores);
+	let (field_names, field_remap) = field_ids.finish();
+	let mut keyword_map = KeywordMapBuilder::new();
+	for (keyword, item_scores) in sorted_keywords {
+		keyword_map.insert(keyword, renumber_fields(item_scores, &field_remap))?;
 	}
-
-	let fst = fst_builder.into_inner().unwrap();
+	let (fst, keyword_to_items, fields) = keyword_map.finish(field_names)?;
 	let ids_fsst = FsstStrVec::from_strings(&ids);
 
-	Ok(Index {
+	Index::new(
 		fst,
-		ids: ids_fsst,
+		ids_fsst,
 		keyword_to_items,
-	})
+		fields,
+		attributes.finish(),
+		options,
+	)
 }
 
-pub fn search(
-	index: &Index,
-	query: &str,
-	max_results: usize,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-	use fst::automaton::Levenshtein;
-	use fst::map::OpBuilder;
-	use fst::{Automaton, Streamer};
-	use std::collections::HashSet;
+/// The occurrences of one keyword within an item.
+struct Occurrences<'a> {
+	first: (u8, Option<&'a str>),
+	max: (u8, Option<&'a str>),
+	sum: u32,
+	count: u32,
+}
 
-	let map = fst::Map::new(&index.fst)?;
+impl<'a> Occurrences<'a> {
+	fn new(weight: u8, field: Option<&'a str>) -> Self {
+		Occurrences {
+			first: (weight, field),
+			max: (weight, field),
+			sum: weight as u32,
+			count: 1,
+		}
+	}
+
+	fn add(&mut self, weight: u8, field: Option<&'a str>) {
+		if weight > self.max.0 {
+			self.max = (weight, field);
+		}
+		self.sum += weight as u32;
+		self.count += 1;
+	}
 
-	let mut query_words: HashSet<String> = query
-		.split_whitespace()
-		.map(|w| w.to_lowercase())
-		.filter(|w| !w.is_empty())
-		.collect();
+	/// The merged weight and the field of the occurrence it is based on.
+	fn merge(&self, policy: KeywordMerge) -> (u8, Option<&'a str>) {
+		let (max, field) = self.max;
+		match policy {
+			KeywordMerge::First => self.first,
+			KeywordMerge::Max => self.max,
+			KeywordMerge::Sum => (self.sum.min(u8::MAX as u32) as u8, field),
+			KeywordMerge::WeightedByCount => {
+				let weight = max as f32 * (1.0 + (self.count as f32).ln());
+				(weight.round().min(u8::MAX as f32) as u8, field)
+			}
+		}
+	}
+}
 
-	query_words.insert(query.to_lowercase());
+/// Extracts the lowercased keywords of an item with the name of their field,
+/// combining the weights of repeated keywords as `policy` says. Terms without
+/// a field come first, then the fields in order of their names.
+fn item_keywords(item: &InputItem, policy: KeywordMerge) -> Vec<(String, u8, Option<&str>)> {
+	let mut positions: HashMap<String, usize> = HashMap::new();
+	let mut keywords: Vec<(String, Occurrences)> = Vec::new();
 
-	let mut keyword_indices: Vec<u64> = Vec::new();
+	let term_lists = std::iter::once((None, &item.search_terms)).chain(
+		item
+			.fields
+			.iter()
+			.map(|(name, terms)| (Some(name.as_str()), terms)),
+	);
+	for (field, terms) in term_lists {
+		for (tokens, weight) in terms {
+			let words: Box<dyn Iterator<Item = &str>> = match tokens {
+				SearchTokens::Raw(raw) => Box::new(raw.split_whitespace()),
+				SearchTokens::Tokens(tokens) => Box::new(tokens.iter().map(String::as_str)),
+			};
+
+			for keyword in words {
+				let keyword = keyword.to_lowercase();
+				if keyword.is_empty() {
+					continue;
+				}
+				match positions.get(&keyword) {
+					Some(&position) => keywords[position].1.add(*weight, field),
+					None => {
+						positions.insert(keyword.clone(), keywords.len());
+						keywords.push((keyword, Occurrences::new(*weight, field)));
+					}
+				}
+			}
+		}
+	}
 
-	for query_word in query_words {
-		use fst::automaton::Str;
+	keywords
+		.into_iter()
+		.map(|(keyword, occurrences)| {
+			let (weight, field) = occurrences.merge(policy);
+			(keyword, weight, field)
+		})
+		.collect()
+}
 
-		let lev = Levenshtein::new(query_word.as_str(), 1)?;
-		let prefix = Str::new(query_word.as_str()).starts_with();
+/// Replaces the provisional field numbers of `item_scores` with final ones.
+fn renumber_fields(mut item_scores: FieldPostings, remap: &[u8]) -> FieldPostings {
+	for (_, _, field) in &mut item_scores {
+		*field = remap[*field as usize];
+	}
+	item_scores
+}
 
-		let mut op = OpBuilder::new()
-			.add(map.search(lev))
-			.add(map.search(prefix))
-			.union();
+/// Builds the FST, posting lists and posting fields from keywords inserted in
+/// sorted order.
+///
+/// Postings are ordered by descending weight, ties by ascending item index.
+struct KeywordMapBuilder {
+	fst_builder: fst::MapBuilder<Vec<u8>>,
+	keyword_to_items: Vec<Postings>,
+	posting_fields: Vec<Vec<u8>>,
+}
 
-		while let Some((_keyword, indexed_value)) = op.next() {
-			let keyword_index = indexed_value.to_vec().get(0).unwrap().value;
-			keyword_indices.push(keyword_index);
+impl KeywordMapBuilder {
+	fn new() -> Self {
+		KeywordMapBuilder {
+			fst_builder: fst::MapBuilder::memory(),
+			keyword_to_items: Vec::new(),
+			posting_fields: Vec::new(),
 		}
 	}
 
-	let mut items: HashMap<usize, u8> = HashMap::new();
+	fn insert(
+		&mut self,
+		keyword: impl AsRef<[u8]>,
+		mut item_scores: FieldPostings,
+	) -> Result<(), Box<dyn std::error::Error>> {
+		self
+			.fst_builder
+			.insert(keyword, self.keyword_to_items.len() as u64)?;
+
+		item_scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+		if item_scores.iter().any(|(_, _, field)| *field != 0) {
+			self
+				.posting_fields
+				.resize(self.keyword_to_items.len(), Vec::new());
+			self
+				.posting_fields
+				.push(item_scores.iter().map(|(_, _, field)| *field).collect());
+		}
+		self.keyword_to_items.push(
+			item_scores
+				.into_iter()
+				.map(|(item_index, weight, _)| (item_index, weight))
+				.collect(),
+		);
+		Ok(())
+	}
 
-	for keyword_index in keyword_indices {
-		let matching_items = &index.keyword_to_items[keyword_index as usize];
+	fn finish(self, field_names: Vec<String>) -> Result<KeywordMap, Box<dyn std::error::Error>> {
+		Ok((
+			self.fst_builder.into_inner()?,
+			self.keyword_to_items,
+			Fields::new(field_names, self.posting_fields),
+		))
+	}
+}
 
-		for (item_index, score) in matching_items {
-			let entry = items.entry(*item_index).or_insert(0);
-			*entry = entry.saturating_add(*score);
+/// Unions the keywords of several indexes into a new FST and posting lists.
+///
+/// Each segment comes with a table mapping its item indices to positions in the
+/// resulting index; items mapped to `None` are dropped, and keywords left
+/// without postings are omitted.
+fn merge_segments(
+	segments: &[(&Index, Vec<Option<usize>>)],
+) -> Result<KeywordMap, Box<dyn std::error::Error>> {
+	use fst::Streamer;
+
+	let mut field_ids = FieldIds::new();
+	let mut field_remaps: Vec<Vec<u8>> = Vec::with_capacity(segments.len());
+	for (index, _) in segments {
+		let mut remap = vec![0];
+		for name in index.fields.names() {
+			remap.push(field_ids.id(Some(name))?);
 		}
+		field_remaps.push(remap);
 	}
+	let (field_names, final_remap) = field_ids.finish();
 
-	let mut items: Vec<(usize, u8)> = items.into_iter().collect();
-	items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
-	items.truncate(max_results);
-
-	let mut result: Vec<String> = Vec::new();
+	let maps = segments
+		.iter()
+		.map(|(index, _)| fst::Map::new(index.fst.as_slice()))
+		.collect::<Result<Vec<_>, _>>()?;
 
-	for (item_index, _) in items {
-		let id = index
-			.ids
-			.get(item_index)
-			.ok_or_else(|| "Failed to get item id")?;
+	let mut op = fst::map::OpBuilder::new();
+	for map in &maps {
+		op = op.add(map.stream());
+	}
+	let mut union = op.union();
+
+	let mut keyword_map = KeywordMapBuilder::new();
+	while let Some((keyword, indexed_values)) = union.next() {
+		let mut item_scores = Vec::new();
+		for indexed_value in indexed_values {
+			let (index, remap) = &segments[indexed_value.index];
+			let postings = index
+				.keyword_to_items
+				.get(indexed_value.value as usize)
+				.ok_or("Keyword index out of bounds")?;
+			let fields = index.fields.posting_fields(indexed_value.value as usize);
+			let field_remap = &field_remaps[indexed_value.index];
+
+			for (position, (item_index, score)) in postings.iter().enumerate() {
+				if let Some(Some(new_index)) = remap.get(*item_index) {
+					let field = match fields.get(position) {
+						Some(&field) => *field_remap
+							.get(field as usize)
+							.ok_or("Posting refers to a missing field")?,
+						None => 0,
+					};
+					item_scores.push((*new_index, *score, field));
+				}
+			}
+		}
 
-		result.push(id);
+		if !item_scores.is_empty() {
+			keyword_map.insert(keyword, renumber_fields(item_scores, &final_remap))?;
+		}
 	}
 
-	Ok(result)
+	keyword_map.finish(field_names)
 }
 
 #[cfg(test)]
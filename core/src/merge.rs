@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::{AttributesBuilder, FsstStrVec, Index, merge_segments};
+
+/// How [`Index::merge`] resolves items that share an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+	/// Keep the item from the earliest index and drop the others.
+	#[default]
+	KeepFirst,
+	/// Keep the item from the latest index and drop the others.
+	KeepLast,
+	/// Fail the merge.
+	Error,
+}
+
+impl Index {
+	/// Merges several indexes into one.
+	///
+	/// Items are laid out in the order of `indexes`, the FSTs are unioned and
+	/// the IDs are recompressed with a dictionary trained on all of them. The
+	/// result keeps the build options of the first index.
+	pub fn merge(
+		indexes: &[Index],
+		policy: DuplicatePolicy,
+	) -> Result<Index, Box<dyn std::error::Error>> {
+		let mut sources: Vec<Option<(usize, usize, String)>> = Vec::new();
+		let mut kept: HashMap<String, usize> = HashMap::new();
+
+		for (source_index, index) in indexes.iter().enumerate() {
+			for (item_index, id) in index.ids.iter().enumerate() {
+				let id = id?;
+
+				match kept.get(&id) {
+					Some(&existing) => match policy {
+						DuplicatePolicy::KeepFirst => continue,
+						DuplicatePolicy::KeepLast => {
+							kept.insert(id.clone(), sources.len());
+							sources[existing] = None;
+						}
+						DuplicatePolicy::Error => {
+							return Err(format!("Duplicate item id '{}'", id).into());
+						}
+					},
+					None => {
+						kept.insert(id.clone(), sources.len());
+					}
+				}
+
+				sources.push(Some((source_index, item_index, id)));
+			}
+		}
+
+		let mut remaps: Vec<Vec<Option<usize>>> = indexes
+			.iter()
+			.map(|index| vec![None; index.len()])
+			.collect();
+		let mut ids: Vec<String> = Vec::with_capacity(kept.len());
+		let mut source_attributes: Vec<_> = indexes
+			.iter()
+			.map(|index| index.attributes.items(index.len()))
+			.collect();
+		let mut attributes = AttributesBuilder::new();
+
+		for (source_index, item_index, id) in sources.into_iter().flatten() {
+			remaps[source_index][item_index] = Some(ids.len());
+			ids.push(id);
+			attributes.push(std::mem::take(
+				&mut source_attributes[source_index][item_index],
+			));
+		}
+
+		let segments: Vec<(&Index, Vec<Option<usize>>)> = indexes.iter().zip(remaps).collect();
+		let (fst, keyword_to_items, fields) = merge_segments(&segments)?;
+
+		Index::new(
+			fst,
+			FsstStrVec::from_strings(&ids),
+			keyword_to_items,
+			fields,
+			attributes.finish(),
+			indexes
+				.first()
+				.map(|index| index.build_options)
+				.unwrap_or_default(),
+		)
+	}
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::correct::correct_query;
+use crate::search::{Ranking, compare_rankings, search_sorted};
+use crate::suggest::{Completions, TopCompletions, completions};
+use crate::{FacetCounts, FacetValueCount, Index, SearchOptions, SuggestOptions, Suggestion};
+
+/// A set of named indexes searched together.
+///
+/// Scores are raw sums of the build-time term weights with no per-index
+/// normalization, so hits from different indexes are directly comparable as
+/// long as they were built with the same weighting scheme.
+#[derive(Debug, Default)]
+pub struct MultiIndex {
+	sources: Vec<(String, Index)>,
+}
+
+/// A search result tagged with the index it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MultiSearchHit {
+	pub source: String,
+	pub id: String,
+	pub score: u8,
+	/// See [`SearchHit::collapsed`](crate::SearchHit::collapsed).
+	pub collapsed: usize,
+}
+
+/// One page of merged results from a [`MultiIndex`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MultiSearchResults {
+	pub hits: Vec<MultiSearchHit>,
+	/// Number of items matching the query, summed over all indexes.
+	pub total: usize,
+	/// Facet value counts, summed over all indexes.
+	pub facets: Vec<FacetCounts>,
+	/// A corrected query to offer when nothing matched in any index.
+	pub correction: Option<String>,
+}
+
+impl MultiIndex {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds an index under `name`, replacing any index previously added with
+	/// the same name.
+	pub fn add(&mut self, name: impl Into<String>, index: Index) {
+		let name = name.into();
+		match self.sources.iter_mut().find(|(source, _)| *source == name) {
+			Some(source) => source.1 = index,
+			None => self.sources.push((name, index)),
+		}
+	}
+
+	/// Removes the index added under `name`.
+	pub fn remove(&mut self, name: &str) -> Option<Index> {
+		let position = self.sources.iter().position(|(source, _)| source == name)?;
+		Some(self.sources.remove(position).1)
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Index> {
+		self
+			.sources
+			.iter()
+			.find(|(source, _)| source == name)
+			.map(|(_, index)| index)
+	}
+
+	/// Names of the indexes in the order they were added.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.sources.iter().map(|(name, _)| name.as_str())
+	}
+
+	pub fn len(&self) -> usize {
+		self.sources.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.sources.is_empty()
+	}
+
+	/// Searches every index and merges the results by score.
+	///
+	/// Ties are broken by the order the indexes were added in, then by the
+	/// rank within each index. With sort keys or item boosts, results are
+	/// merged the way each index ranks them.
+	pub fn search(
+		&self,
+		query: &str,
+		max_results: usize,
+	) -> Result<Vec<MultiSearchHit>, Box<dyn std::error::Error>> {
+		let options = SearchOptions {
+			max_results,
+			..Default::default()
+		};
+		Ok(self.search_with_options(query, &options)?.hits)
+	}
+
+	/// Like [`MultiIndex::search`], with `options.offset` applied to the
+	/// merged results. With `options.collapse`, groups are collapsed within
+	/// each index, so a group found in several indexes appears once for each.
+	pub fn search_with_options(
+		&self,
+		query: &str,
+		options: &SearchOptions,
+	) -> Result<MultiSearchResults, Box<dyn std::error::Error>> {
+		let source_options = SearchOptions {
+			max_results: options.offset.saturating_add(options.max_results),
+			offset: 0,
+			..options.clone()
+		};
+		let mut hits: Vec<(Ranking, usize, usize, MultiSearchHit)> = Vec::new();
+		let mut total = 0;
+		let mut facet_counts: Vec<HashMap<String, usize>> = vec![HashMap::new(); options.facets.len()];
+
+		for (source_index, (name, index)) in self.sources.iter().enumerate() {
+			let (results, rankings) = search_sorted(index, query, &source_options)?;
+			total += results.total;
+
+			for (counts, facet) in facet_counts.iter_mut().zip(results.facets) {
+				for value in facet.values {
+					*counts.entry(value.value).or_default() += value.count;
+				}
+			}
+
+			for (rank, (hit, ranking)) in results.hits.into_iter().zip(rankings).enumerate() {
+				hits.push((
+					ranking,
+					source_index,
+					rank,
+					MultiSearchHit {
+						source: name.clone(),
+						id: hit.id,
+						score: hit.score,
+						collapsed: hit.collapsed,
+					},
+				));
+			}
+		}
+
+		hits.sort_by(|a, b| {
+			compare_rankings(&a.0, &b.0, &options.sort)
+				.then_with(|| a.1.cmp(&b.1))
+				.then_with(|| a.2.cmp(&b.2))
+		});
+
+		let facets = options
+			.facets
+			.iter()
+			.zip(facet_counts)
+			.map(|(facet, counts)| {
+				let mut values: Vec<FacetValueCount> = counts
+					.into_iter()
+					.map(|(value, count)| FacetValueCount { value, count })
+					.collect();
+				values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+				FacetCounts {
+					facet: facet.clone(),
+					values,
+				}
+			})
+			.collect();
+
+		let correction = if total == 0 {
+			let indexes: Vec<&Index> = self.sources.iter().map(|(_, index)| index).collect();
+			correct_query(&indexes, query, options)?
+		} else {
+			None
+		};
+
+		Ok(MultiSearchResults {
+			hits: hits
+				.into_iter()
+				.skip(options.offset)
+				.take(options.max_results)
+				.map(|(_, _, _, hit)| hit)
+				.collect(),
+			total,
+			facets,
+			correction,
+		})
+	}
+
+	/// Suggests completions of the last word of `query` from every index,
+	/// summing the frequencies and weights of keywords found in several.
+	pub fn suggest(
+		&self,
+		query: &str,
+		options: &SuggestOptions,
+	) -> Result<Vec<Suggestion>, Box<dyn std::error::Error>> {
+		let mut merged: HashMap<String, (usize, u64)> = HashMap::new();
+		for (_, index) in &self.sources {
+			completions(index, query, &mut merged)?;
+		}
+
+		let mut top = TopCompletions::new(options);
+		for (keyword, (frequency, weight)) in &merged {
+			top.add(keyword, *frequency, *weight);
+		}
+		Ok(top.into_suggestions(query))
+	}
+}
@@ -0,0 +1,253 @@
+use fst::Automaton;
+
+use crate::typo::{Utf8Step, utf8_step};
+
+/// Longest pattern accepted, in bytes.
+const MAX_PATTERN_LENGTH: usize = 100;
+
+/// Most pattern elements, so that the set of positions fits in a `u128`.
+const MAX_ATOMS: usize = 127;
+
+/// A query word matching keywords by pattern: a wildcard such as `prog*ing`,
+/// where `*` stands for any number of characters, or a regular expression
+/// between slashes such as `/v[0-9]+/`.
+///
+/// Regular expressions are limited to characters, `.`, classes such as
+/// `[a-z]` or `[^0-9]`, the escapes `\d` and `\w`, and the quantifiers `*`,
+/// `+` and `?`; `^` and `$` are allowed at the ends and mean nothing, since
+/// patterns always match whole keywords. Other escaped letters, such as `\D`,
+/// are not supported. Characters are lowercased like keywords, but escapes
+/// are read as written. Patterns are matched by following
+/// every position at once rather than by backtracking, so matching a keyword
+/// takes time linear in its length whatever the pattern. Patterns must start
+/// with a character so that only the keywords starting with it are visited.
+#[derive(Debug)]
+pub(crate) struct Pattern {
+	atoms: Vec<(Atom, Repeat)>,
+}
+
+#[derive(Debug, Clone)]
+enum Atom {
+	Char(char),
+	Any,
+	Class {
+		ranges: Vec<(char, char)>,
+		negated: bool,
+	},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+	One,
+	Optional,
+	Any,
+}
+
+/// The positions in the pattern reached so far as a bit set, and the bytes of
+/// a character not yet complete. `None` once nothing can match.
+#[derive(Debug, Clone)]
+pub(crate) struct PatternState {
+	positions: u128,
+	pending: Vec<u8>,
+}
+
+impl Atom {
+	fn matches(&self, c: char) -> bool {
+		match self {
+			Atom::Char(expected) => c == *expected,
+			Atom::Any => true,
+			Atom::Class { ranges, negated } => {
+				ranges
+					.iter()
+					.any(|(start, end)| (*start..=*end).contains(&c))
+					!= *negated
+			}
+		}
+	}
+}
+
+impl Pattern {
+	/// Parses `word`, as written in the query, as a pattern, or returns
+	/// nothing if it is not one, is not valid, or does not start with a
+	/// character.
+	pub fn parse(word: &str) -> Option<Self> {
+		if word.len() > MAX_PATTERN_LENGTH || word.contains(char::is_whitespace) {
+			return None;
+		}
+		let atoms = match word
+			.strip_prefix('/')
+			.and_then(|rest| rest.strip_suffix('/'))
+		{
+			Some(regex) if !regex.is_empty() => parse_regex(regex)?,
+			_ if word.contains('*') => parse_wildcard(word),
+			_ => return None,
+		};
+		if atoms.len() > MAX_ATOMS || !matches!(atoms.first(), Some((Atom::Char(_), Repeat::One))) {
+			return None;
+		}
+		Some(Pattern { atoms })
+	}
+
+	/// Adds the positions reachable by skipping optional elements.
+	fn close(&self, mut positions: u128) -> u128 {
+		for (i, (_, repeat)) in self.atoms.iter().enumerate() {
+			if positions & (1 << i) != 0 && *repeat != Repeat::One {
+				positions |= 1 << (i + 1);
+			}
+		}
+		positions
+	}
+}
+
+fn parse_wildcard(word: &str) -> Vec<(Atom, Repeat)> {
+	let mut atoms = Vec::new();
+	for c in word.chars() {
+		match c {
+			'*' if matches!(atoms.last(), Some((Atom::Any, Repeat::Any))) => {}
+			'*' => atoms.push((Atom::Any, Repeat::Any)),
+			c => atoms.extend(c.to_lowercase().map(|c| (Atom::Char(c), Repeat::One))),
+		}
+	}
+	atoms
+}
+
+fn parse_regex(regex: &str) -> Option<Vec<(Atom, Repeat)>> {
+	let regex = regex.strip_prefix('^').unwrap_or(regex);
+	let regex = regex.strip_suffix('$').unwrap_or(regex);
+	let chars: Vec<char> = regex.chars().collect();
+	let mut atoms: Vec<(Atom, Repeat)> = Vec::new();
+
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		i += 1;
+		let atom = match c {
+			'.' => Atom::Any,
+			'\\' => {
+				i += 1;
+				parse_escape(*chars.get(i - 1)?)?
+			}
+			'[' => {
+				let negated = chars.get(i) == Some(&'^');
+				if negated {
+					i += 1;
+				}
+				let mut ranges = Vec::new();
+				loop {
+					let start = match *chars.get(i)? {
+						']' if !ranges.is_empty() => break,
+						'\\' => {
+							i += 1;
+							*chars.get(i)?
+						}
+						c => c,
+					};
+					i += 1;
+					let mut end = start;
+					if chars.get(i) == Some(&'-') && chars.get(i + 1).is_some_and(|&c| c != ']') {
+						end = chars[i + 1];
+						i += 2;
+					}
+					let (start, end) = (lowercase(start)?, lowercase(end)?);
+					if start > end {
+						return None;
+					}
+					ranges.push((start, end));
+				}
+				i += 1;
+				Atom::Class { ranges, negated }
+			}
+			'*' | '+' | '?' => {
+				let (atom, repeat) = atoms.last_mut()?;
+				if *repeat != Repeat::One {
+					return None;
+				}
+				match c {
+					'*' => *repeat = Repeat::Any,
+					'?' => *repeat = Repeat::Optional,
+					_ => {
+						let atom = atom.clone();
+						atoms.push((atom, Repeat::Any));
+					}
+				}
+				continue;
+			}
+			'(' | ')' | '|' | '{' | '}' | '^' | '$' | ']' => return None,
+			c => Atom::Char(lowercase(c)?),
+		};
+		atoms.push((atom, Repeat::One));
+	}
+	Some(atoms)
+}
+
+fn parse_escape(c: char) -> Option<Atom> {
+	match c {
+		'd' => Some(Atom::Class {
+			ranges: vec![('0', '9')],
+			negated: false,
+		}),
+		'w' => Some(Atom::Class {
+			ranges: vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+			negated: false,
+		}),
+		c if c.is_alphanumeric() => None,
+		c => Some(Atom::Char(c)),
+	}
+}
+
+/// The lowercase form of `c`, if it is a single character.
+fn lowercase(c: char) -> Option<char> {
+	let mut lower = c.to_lowercase();
+	let c = lower.next()?;
+	lower.next().is_none().then_some(c)
+}
+
+impl Automaton for Pattern {
+	type State = Option<PatternState>;
+
+	fn start(&self) -> Self::State {
+		Some(PatternState {
+			positions: self.close(1),
+			pending: Vec::new(),
+		})
+	}
+
+	fn is_match(&self, state: &Self::State) -> bool {
+		state.as_ref().is_some_and(|state| {
+			state.pending.is_empty() && state.positions & (1 << self.atoms.len()) != 0
+		})
+	}
+
+	fn can_match(&self, state: &Self::State) -> bool {
+		state.is_some()
+	}
+
+	fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+		let state = state.as_ref()?;
+		let c = match utf8_step(&state.pending, byte) {
+			Utf8Step::Char(c) => c,
+			Utf8Step::Partial(pending) => {
+				return Some(PatternState {
+					positions: state.positions,
+					pending,
+				});
+			}
+			Utf8Step::Invalid => return None,
+		};
+
+		let mut positions = 0;
+		for (i, (atom, repeat)) in self.atoms.iter().enumerate() {
+			if state.positions & (1 << i) != 0 && atom.matches(c) {
+				positions |= match repeat {
+					Repeat::Any => 1 << i,
+					_ => 1 << (i + 1),
+				};
+			}
+		}
+		let positions = self.close(positions);
+		(positions != 0).then(|| PatternState {
+			positions,
+			pending: Vec::new(),
+		})
+	}
+}
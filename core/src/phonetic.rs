@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use fst::Streamer;
+use serde::{Deserialize, Serialize};
+
+/// Keywords grouped by how they sound, so that `jonson` can find `johnson`.
+///
+/// A second FST maps the phonetic key of every keyword to a position in
+/// `keywords`, which lists the keywords with that key. It is only built when
+/// [`BuildOptions::phonetic`](crate::BuildOptions::phonetic) is set, and
+/// is empty otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PhoneticIndex {
+	fst: Vec<u8>,
+	keywords: Vec<Vec<u32>>,
+}
+
+impl PhoneticIndex {
+	/// Groups the keywords of the FST `keywords` by their phonetic key.
+	pub fn new(keywords: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+		let map = fst::Map::new(keywords)?;
+		let mut groups: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+		let mut stream = map.stream();
+		while let Some((keyword, keyword_index)) = stream.next() {
+			if let Some(key) = std::str::from_utf8(keyword).ok().and_then(phonetic_key) {
+				groups.entry(key).or_default().push(keyword_index as u32);
+			}
+		}
+
+		let mut builder = fst::MapBuilder::memory();
+		let mut keywords = Vec::with_capacity(groups.len());
+		for (key, keyword_indices) in groups {
+			builder.insert(key, keywords.len() as u64)?;
+			keywords.push(keyword_indices);
+		}
+		Ok(PhoneticIndex {
+			fst: builder.into_inner()?,
+			keywords,
+		})
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.keywords.is_empty()
+	}
+
+	/// The keywords sounding like `word`.
+	pub fn keywords(&self, word: &str) -> Result<&[u32], Box<dyn std::error::Error>> {
+		let Some(key) = phonetic_key(word) else {
+			return Ok(&[]);
+		};
+		let map = fst::Map::new(self.fst.as_slice())?;
+		Ok(match map.get(key) {
+			Some(position) => self
+				.keywords
+				.get(position as usize)
+				.map_or(&[], Vec::as_slice),
+			None => &[],
+		})
+	}
+
+	/// Checks the FST checksum, since traversing a corrupted FST can panic.
+	pub fn check(&self) -> Result<(), Box<dyn std::error::Error>> {
+		if self.is_empty() {
+			return Ok(());
+		}
+		let map = fst::Map::new(self.fst.as_slice())?;
+		map
+			.as_fst()
+			.verify()
+			.map_err(|e| format!("Phonetic FST is corrupted: {}", e).into())
+	}
+}
+
+/// The Metaphone key of `word`, or nothing if it has no ASCII letters.
+///
+/// Only ASCII letters are considered. Unlike the original algorithm, keys are
+/// not truncated and every leading vowel becomes `A`.
+pub(crate) fn phonetic_key(word: &str) -> Option<String> {
+	let letters: Vec<u8> = word
+		.bytes()
+		.filter(u8::is_ascii_alphabetic)
+		.map(|byte| byte.to_ascii_uppercase())
+		.collect();
+	if letters.is_empty() {
+		return None;
+	}
+
+	let mut letters = letters.as_slice();
+	let mut key = String::new();
+	match letters {
+		[b'A', b'E', ..] | [b'G' | b'K' | b'P', b'N', ..] | [b'W', b'R', ..] => letters = &letters[1..],
+		[b'X', rest @ ..] => {
+			key.push('S');
+			letters = rest;
+		}
+		[b'W', b'H', rest @ ..] => {
+			key.push('W');
+			letters = rest;
+		}
+		_ => {}
+	}
+
+	let at = |i: usize| letters.get(i).copied().unwrap_or(0);
+	let is_vowel = |c: u8| matches!(c, b'A' | b'E' | b'I' | b'O' | b'U');
+	let is_front_vowel = |c: u8| matches!(c, b'E' | b'I' | b'Y');
+
+	for i in 0..letters.len() {
+		let (previous, c, next) = (if i > 0 { at(i - 1) } else { 0 }, at(i), at(i + 1));
+		if c == previous && c != b'C' {
+			continue;
+		}
+		match c {
+			b'A' | b'E' | b'I' | b'O' | b'U' => {
+				if i == 0 && key.is_empty() {
+					key.push('A');
+				}
+			}
+			b'B' => {
+				if !(previous == b'M' && i + 1 == letters.len()) {
+					key.push('B');
+				}
+			}
+			b'C' => {
+				if next == b'I' && at(i + 2) == b'A' {
+					key.push('X');
+				} else if next == b'H' {
+					key.push(if previous == b'S' { 'K' } else { 'X' });
+				} else if is_front_vowel(next) {
+					if previous != b'S' {
+						key.push('S');
+					}
+				} else {
+					key.push('K');
+				}
+			}
+			b'D' => {
+				if next == b'G' && is_front_vowel(at(i + 2)) {
+					key.push('J');
+				} else {
+					key.push('T');
+				}
+			}
+			b'G' => {
+				let silent_gh = next == b'H' && i + 2 < letters.len() && !is_vowel(at(i + 2));
+				let silent_gn = next == b'N' && (i + 2 == letters.len() || &letters[i + 1..] == b"NED");
+				if silent_gh || silent_gn {
+					continue;
+				}
+				if is_front_vowel(next) && previous != b'G' {
+					key.push('J');
+				} else {
+					key.push('K');
+				}
+			}
+			b'H' => {
+				let after_vowel = is_vowel(previous) && !is_vowel(next);
+				if !after_vowel && !matches!(previous, b'C' | b'S' | b'P' | b'T' | b'G') {
+					key.push('H');
+				}
+			}
+			b'K' => {
+				if previous != b'C' {
+					key.push('K');
+				}
+			}
+			b'P' => key.push(if next == b'H' { 'F' } else { 'P' }),
+			b'Q' => key.push('K'),
+			b'S' => {
+				if next == b'H' || (next == b'I' && matches!(at(i + 2), b'O' | b'A')) {
+					key.push('X');
+				} else {
+					key.push('S');
+				}
+			}
+			b'T' => {
+				if next == b'I' && matches!(at(i + 2), b'O' | b'A') {
+					key.push('X');
+				} else if next == b'H' {
+					key.push('0');
+				} else if !(next == b'C' && at(i + 2) == b'H') {
+					key.push('T');
+				}
+			}
+			b'V' => key.push('F'),
+			b'W' | b'Y' => {
+				if is_vowel(next) {
+					key.push(c as char);
+				}
+			}
+			b'X' => key.push_str("KS"),
+			b'Z' => key.push('S'),
+			_ => key.push(c as char),
+		}
+	}
+
+	(!key.is_empty()).then_some(key)
+}
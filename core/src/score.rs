@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::attributes::ItemSet;
+use crate::{BoostMode, Postings};
+
+thread_local! {
+	/// The score of each item, kept between queries and cleared after each, so
+	/// that a query costs as much as the postings it reads rather than the
+	/// number of items.
+	static SCORES: Cell<Vec<Option<u8>>> = const { Cell::new(Vec::new()) };
+}
+
+/// The outcome of [`top_items`].
+pub(crate) struct Scores {
+	/// The best `(item index, score)` pairs, best first.
+	pub top: Postings,
+	/// The items that matched, in no particular order.
+	pub matched: Vec<usize>,
+}
+
+/// Item boosts to rank by, combined with the scores according to `mode`.
+pub(crate) struct Boosts<'a> {
+	pub mode: BoostMode,
+	/// The boost of each item.
+	pub boosts: &'a [Option<f32>],
+	/// The largest of `boosts`.
+	pub max: f32,
+}
+
+impl Boosts<'_> {
+	fn rank(&self, item_index: usize, score: u8) -> f32 {
+		let boost = self.boosts.get(item_index).copied().flatten();
+		self.mode.apply(score, boost)
+	}
+
+	/// The highest rank of any item scoring at most `score`. Boosts are never
+	/// negative, so ranks do not decrease as scores grow.
+	fn best_rank(&self, score: u8) -> f32 {
+		self.mode.apply(score, Some(self.max)).max(score as f32)
+	}
+}
+
+/// Sums the weights each item gets from `lists` and returns the `k` best
+/// `(item index, score)` pairs, by descending rank and then ascending item.
+/// The rank is the score, boosted by `boosts` if given. Only items in
+/// `allowed` are considered, if given.
+///
+/// Scores saturate at `u8::MAX`. Posting lists are sorted by descending
+/// weight, so the first posting of a list is its maximum weight. Lists are
+/// scored from the highest maximum down; once the maxima of the remaining
+/// lists, boosted by the largest boost, add up to less than the current
+/// `k`-th best rank, items not seen so far cannot reach the results, so the
+/// remaining lists only add to the items already found and merely count new
+/// ones.
+pub(crate) fn top_items(
+	lists: Vec<&Postings>,
+	item_count: usize,
+	k: usize,
+	allowed: Option<&ItemSet>,
+	boosts: Option<&Boosts>,
+) -> Result<Scores, Box<dyn std::error::Error>> {
+	if lists.is_empty() {
+		return Ok(Scores {
+			top: Vec::new(),
+			matched: Vec::new(),
+		});
+	}
+	let mut scores = SCORES.take();
+	if scores.len() < item_count {
+		scores.resize(item_count, None);
+	}
+	let result = score_items(&mut scores[..item_count], lists, k, allowed, boosts);
+	// After an error not every score is cleared, so the buffer is dropped.
+	if let Ok(result) = &result {
+		for &item_index in &result.matched {
+			scores[item_index] = None;
+		}
+		SCORES.set(scores);
+	}
+	result
+}
+
+/// [`top_items`] over `scores`, which must all be `None` to begin with.
+fn score_items(
+	scores: &mut [Option<u8>],
+	mut lists: Vec<&Postings>,
+	k: usize,
+	allowed: Option<&ItemSet>,
+	boosts: Option<&Boosts>,
+) -> Result<Scores, Box<dyn std::error::Error>> {
+	let rank = |item_index: usize, score: u8| {
+		boosts.map_or(score as f32, |boosts| boosts.rank(item_index, score))
+	};
+	lists.sort_by_key(|postings| Reverse(max_weight(postings)));
+
+	// remaining[i] is the most that lists[i..] can add to an item's score.
+	let mut remaining = vec![0u32; lists.len() + 1];
+	for i in (0..lists.len()).rev() {
+		remaining[i] = remaining[i + 1] + max_weight(lists[i]) as u32;
+	}
+
+	// The items first seen while scoring come first and are the candidates.
+	let mut matched: Vec<usize> = Vec::new();
+	let mut scored_lists = if k == 0 { 0 } else { lists.len() };
+	// Finding the k-th best rank is linear in the candidates, so only look
+	// again once the bound has moved closer to the last threshold seen.
+	let mut next_check = f32::INFINITY;
+
+	for (i, postings) in lists[..scored_lists].iter().enumerate() {
+		for &(item_index, weight) in postings.iter() {
+			if allowed.is_some_and(|allowed| !allowed.contains(item_index)) {
+				continue;
+			}
+			match scores
+				.get_mut(item_index)
+				.ok_or("Posting refers to a missing item")?
+			{
+				Some(score) => *score = score.saturating_add(weight),
+				score => {
+					*score = Some(weight);
+					matched.push(item_index);
+				}
+			}
+		}
+
+		let bound = remaining[i + 1].min(u8::MAX as u32) as u8;
+		let bound = boosts.map_or(bound as f32, |boosts| boosts.best_rank(bound));
+		if bound < next_check && matched.len() >= k {
+			let threshold = kth_best_rank(scores, &matched, k, rank);
+			if bound < threshold {
+				scored_lists = i + 1;
+				break;
+			}
+			next_check = bound - (bound - threshold) / 2.0;
+		}
+	}
+
+	let candidates = matched.len();
+	for postings in &lists[scored_lists..] {
+		for &(item_index, weight) in postings.iter() {
+			if allowed.is_some_and(|allowed| !allowed.contains(item_index)) {
+				continue;
+			}
+			match scores
+				.get_mut(item_index)
+				.ok_or("Posting refers to a missing item")?
+			{
+				Some(score) => *score = score.saturating_add(weight),
+				score => {
+					*score = Some(weight);
+					matched.push(item_index);
+				}
+			}
+		}
+	}
+
+	let mut heap: BinaryHeap<Reverse<Ranked>> = BinaryHeap::with_capacity(k + 1);
+	for &item_index in &matched[..candidates] {
+		let score = scores[item_index].unwrap_or_default();
+		let key = Ranked {
+			rank: rank(item_index, score),
+			item_index,
+			score,
+		};
+		if heap.len() < k {
+			heap.push(Reverse(key));
+		} else if let Some(mut worst) = heap.peek_mut()
+			&& key > worst.0
+		{
+			*worst = Reverse(key);
+		}
+	}
+
+	let mut top: Vec<Ranked> = heap.into_iter().map(|Reverse(ranked)| ranked).collect();
+	top.sort_by(|a, b| b.cmp(a));
+	let top = top
+		.into_iter()
+		.map(|ranked| (ranked.item_index, ranked.score))
+		.collect();
+	Ok(Scores { top, matched })
+}
+
+fn max_weight(postings: &Postings) -> u8 {
+	postings.first().map_or(0, |(_, weight)| *weight)
+}
+
+fn kth_best_rank(
+	scores: &[Option<u8>],
+	candidates: &[usize],
+	k: usize,
+	rank: impl Fn(usize, u8) -> f32,
+) -> f32 {
+	let mut candidate_ranks: Vec<f32> = candidates
+		.iter()
+		.map(|&item_index| rank(item_index, scores[item_index].unwrap_or_default()))
+		.collect();
+	let (_, kth, _) = candidate_ranks.select_nth_unstable_by(k - 1, |a, b| b.total_cmp(a));
+	*kth
+}
+
+/// An item ordered by rank, then by ascending index, so that better items
+/// compare greater.
+struct Ranked {
+	rank: f32,
+	item_index: usize,
+	score: u8,
+}
+
+impl Ord for Ranked {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self
+			.rank
+			.total_cmp(&other.rank)
+			.then_with(|| other.item_index.cmp(&self.item_index))
+	}
+}
+
+impl PartialOrd for Ranked {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for Ranked {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Ranked {}
@@ -0,0 +1,702 @@
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attributes::ItemSet;
+use crate::correct::correct_query;
+use crate::pattern::Pattern;
+use crate::typo::EditDistance;
+use crate::{Index, Postings, score};
+
+/// A search result with its accumulated score.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+	pub id: String,
+	pub score: u8,
+	/// With [`SearchOptions::collapse`], the number of other matches in the
+	/// same group that were left out for this hit.
+	pub collapsed: usize,
+}
+
+/// Controls how a query is matched and how many results are returned.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchOptions {
+	pub max_results: usize,
+	/// Number of best results to skip, for fetching later pages. Ties are
+	/// broken by position in the index, so pages of the same index never
+	/// overlap.
+	pub offset: usize,
+	/// Most keywords a query word may expand to by prefix, typo or pattern
+	/// matching, keeping those that occur in the most items. An exact match
+	/// is always used and does not count towards the limit.
+	pub max_expansions: usize,
+	/// Query words with fewer characters only match by prefix, not within an
+	/// edit distance.
+	pub min_fuzzy_length: usize,
+	/// Query words with at least this many characters match within two
+	/// edits instead of one.
+	pub min_two_typo_length: usize,
+	/// Whether swapping two adjacent characters, as in `pyhton`, counts as
+	/// one edit rather than two.
+	pub transpositions: bool,
+	/// Multiplier for the weights of keywords matched only by sounding like
+	/// a query word, in indexes built with
+	/// [`BuildOptions::phonetic`](crate::BuildOptions::phonetic). `0` turns
+	/// phonetic matching off.
+	pub phonetic_weight: f32,
+	/// Whether query words such as `prog*ing` and `/v[0-9]+/` are matched as
+	/// wildcard and regular expression patterns. Patterns that are not valid
+	/// or do not start with a character are matched as plain words.
+	pub patterns: bool,
+	/// Only items passing every filter are returned.
+	pub filters: Vec<FacetFilter>,
+	/// Facets whose values are counted over all matching items.
+	pub facets: Vec<String>,
+	/// Only items whose numeric attributes lie within every range are
+	/// returned.
+	pub ranges: Vec<RangeFilter>,
+	/// Numeric attributes to order results by, with the score breaking ties.
+	/// Without sort keys, results are ordered by score.
+	pub sort: Vec<SortKey>,
+	/// How item boosts affect the ranking. [`SearchHit::score`] is always
+	/// the query score before boosting.
+	pub boost: BoostMode,
+	/// Multipliers for the weights of terms from each field, such as
+	/// `{"title": 2.0}`. Boosted weights saturate at 255.
+	pub field_boosts: BTreeMap<String, f32>,
+	/// Returns only the best hit of each group of items, counting the rest
+	/// in [`SearchHit::collapsed`]. Items without a group are never
+	/// collapsed.
+	pub collapse: bool,
+}
+
+impl Default for SearchOptions {
+	fn default() -> Self {
+		SearchOptions {
+			max_results: 10,
+			offset: 0,
+			max_expansions: 100,
+			min_fuzzy_length: 3,
+			min_two_typo_length: 8,
+			transpositions: true,
+			phonetic_weight: 0.5,
+			patterns: true,
+			filters: Vec::new(),
+			facets: Vec::new(),
+			ranges: Vec::new(),
+			sort: Vec::new(),
+			boost: BoostMode::default(),
+			field_boosts: BTreeMap::new(),
+			collapse: false,
+		}
+	}
+}
+
+/// Passes items having any of `values` for `facet`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FacetFilter {
+	pub facet: String,
+	pub values: Vec<String>,
+}
+
+/// Passes items whose value of `attribute` lies within `min..=max`. Items
+/// without a value never pass.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RangeFilter {
+	pub attribute: String,
+	pub min: Option<f64>,
+	pub max: Option<f64>,
+}
+
+impl RangeFilter {
+	fn contains(&self, value: f64) -> bool {
+		self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+	}
+}
+
+/// Orders results by a numeric attribute, ascending unless `descending`.
+/// Items without a value come last either way.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SortKey {
+	pub attribute: String,
+	#[serde(default)]
+	pub descending: bool,
+}
+
+/// How the boost of an item is combined with its query score for ranking.
+/// Items without a boost are ranked by their query score in every mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BoostMode {
+	/// Ignore boosts.
+	None,
+	/// `score * boost`
+	#[default]
+	Multiply,
+	/// `score + boost`
+	Add,
+	/// `score * (1 + ln(1 + boost))`, for boosts such as visit counts that
+	/// span several orders of magnitude.
+	Log,
+}
+
+impl BoostMode {
+	pub(crate) fn apply(self, score: u8, boost: Option<f32>) -> f32 {
+		let score = score as f32;
+		match (self, boost) {
+			(BoostMode::None, _) | (_, None) => score,
+			(BoostMode::Multiply, Some(boost)) => score * boost,
+			(BoostMode::Add, Some(boost)) => score + boost,
+			(BoostMode::Log, Some(boost)) => score * (1.0 + boost.ln_1p()),
+		}
+	}
+}
+
+/// What a hit is ranked by: its values for each [`SortKey`], in order, and
+/// then its boosted score.
+pub(crate) struct Ranking {
+	sort_values: Vec<Option<f64>>,
+	score: f32,
+}
+
+/// How many matching items have each value of a facet, most frequent first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetCounts {
+	pub facet: String,
+	pub values: Vec<FacetValueCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetValueCount {
+	pub value: String,
+	pub count: usize,
+}
+
+/// The hits of a search along with how the query was expanded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+	pub hits: Vec<SearchHit>,
+	/// Number of items matching the query, on all pages. With
+	/// [`SearchOptions::collapse`], the number of hits left after collapsing.
+	pub total: usize,
+	/// Value counts of the facets in [`SearchOptions::facets`], in that order.
+	pub facets: Vec<FacetCounts>,
+	/// A corrected query to offer when nothing matched, such as `"rust"`
+	/// for `"rsut"`.
+	pub correction: Option<String>,
+	pub stats: SearchStats,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchStats {
+	/// Distinct query words, including the whole query.
+	pub query_words: usize,
+	/// Keywords matched by the query words before applying `max_expansions`.
+	pub matched_keywords: usize,
+	/// Keywords whose postings were scored.
+	pub expanded_keywords: usize,
+}
+
+pub fn search(
+	index: &Index,
+	query: &str,
+	max_results: usize,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+	let hits = search_hits(index, query, max_results)?;
+	Ok(hits.into_iter().map(|hit| hit.id).collect())
+}
+
+/// Like [`search`], but also returns the score of each result.
+pub fn search_hits(
+	index: &Index,
+	query: &str,
+	max_results: usize,
+) -> Result<Vec<SearchHit>, Box<dyn std::error::Error>> {
+	let options = SearchOptions {
+		max_results,
+		..Default::default()
+	};
+	Ok(search_with_options(index, query, &options)?.hits)
+}
+
+pub fn search_with_options(
+	index: &Index,
+	query: &str,
+	options: &SearchOptions,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+	let mut results = search_sorted(index, query, options)?.0;
+	if results.total == 0 {
+		results.correction = correct_query(&[index], query, options)?;
+	}
+	Ok(results)
+}
+
+/// Like [`search_with_options`], also returning what each hit was ranked by.
+pub(crate) fn search_sorted(
+	index: &Index,
+	query: &str,
+	options: &SearchOptions,
+) -> Result<(SearchResults, Vec<Ranking>), Box<dyn std::error::Error>> {
+	let map = fst::Map::new(&index.fst)?;
+	let (query_words, patterns) = parse_query(query, options.patterns);
+
+	let mut stats = SearchStats {
+		query_words: query_words.len() + patterns.len(),
+		..Default::default()
+	};
+	let mut keyword_indices: Vec<(u64, f32)> = Vec::new();
+
+	for (pattern, boost) in patterns {
+		let expanded = expand_pattern(index, &map, &pattern, options, &mut stats)?;
+		keyword_indices.extend(
+			expanded
+				.into_iter()
+				.map(|keyword_index| (keyword_index, boost)),
+		);
+	}
+	for (query_word, boost) in query_words {
+		let expanded = expand(index, &map, &query_word, options, &mut stats)?;
+		let phonetic = expand_phonetic(index, &query_word, &expanded, options)?;
+		keyword_indices.extend(
+			expanded
+				.into_iter()
+				.map(|keyword_index| (keyword_index, boost)),
+		);
+		let phonetic_boost = boost * options.phonetic_weight;
+		keyword_indices.extend(
+			phonetic
+				.into_iter()
+				.map(|keyword_index| (keyword_index, phonetic_boost)),
+		);
+	}
+	stats.expanded_keywords = keyword_indices.len();
+
+	let field_boosts: Vec<f32> = std::iter::once(1.0)
+		.chain(
+			index
+				.fields
+				.names()
+				.iter()
+				.map(|name| options.field_boosts.get(name).copied().unwrap_or(1.0)),
+		)
+		.collect();
+	let lists = keyword_indices
+		.iter()
+		.map(|&(keyword_index, boost)| {
+			let postings = index
+				.keyword_to_items
+				.get(keyword_index as usize)
+				.ok_or("Keyword index out of bounds")?;
+			let fields = index.fields.posting_fields(keyword_index as usize);
+			Ok(boost_postings(postings, fields, boost, &field_boosts))
+		})
+		.collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+	let lists: Vec<&Postings> = lists.iter().map(|postings| postings.as_ref()).collect();
+	let allowed = filter_items(index, options);
+	let wanted = options.offset.saturating_add(options.max_results);
+	let boosts = match options.boost {
+		BoostMode::None => None,
+		_ => index.attributes.boosts(),
+	};
+	let ranking_boosts = boosts.map(|boosts| score::Boosts {
+		mode: options.boost,
+		boosts,
+		max: index.attributes.max_boost(),
+	});
+	// Sort keys and collapsing need the scores of every match, so only the
+	// ordering by score, boosted or not, can stop early.
+	let by_rank = options.sort.is_empty() && !options.collapse;
+	let k = if by_rank { wanted } else { index.len() };
+	let scores = score::top_items(
+		lists,
+		index.len(),
+		k,
+		allowed.as_ref(),
+		ranking_boosts.as_ref(),
+	)?;
+	let facets = count_facets(index, &options.facets, &scores.matched);
+
+	let columns: Vec<_> = options
+		.sort
+		.iter()
+		.map(|key| index.attributes.number(&key.attribute))
+		.collect();
+	let mut items: Vec<(Ranking, usize, u8, usize)> = scores
+		.top
+		.into_iter()
+		.map(|(item_index, score)| {
+			let sort_values = columns
+				.iter()
+				.map(|column| column.and_then(|column| column.values.get(item_index).copied().flatten()))
+				.collect();
+			let boost = boosts.and_then(|boosts| boosts.get(item_index).copied().flatten());
+			let ranking = Ranking {
+				sort_values,
+				score: options.boost.apply(score, boost),
+			};
+			(ranking, item_index, score, 0)
+		})
+		.collect();
+	let mut total = scores.matched.len();
+	if !by_rank {
+		items.sort_by(|a, b| compare_rankings(&a.0, &b.0, &options.sort).then_with(|| a.1.cmp(&b.1)));
+		if options.collapse {
+			items = collapse_groups(index, items);
+			total = items.len();
+		}
+		items.truncate(wanted);
+	}
+	items.drain(..options.offset.min(items.len()));
+
+	let ids = index
+		.ids
+		.get_many(items.iter().map(|(_, item_index, _, _)| *item_index))?;
+	let mut hits = Vec::with_capacity(items.len());
+	let mut rankings = Vec::with_capacity(items.len());
+	for (id, (ranking, _, score, collapsed)) in ids.into_iter().zip(items) {
+		hits.push(SearchHit {
+			id,
+			score,
+			collapsed,
+		});
+		rankings.push(ranking);
+	}
+
+	let results = SearchResults {
+		hits,
+		total,
+		facets,
+		correction: None,
+		stats,
+	};
+	Ok((results, rankings))
+}
+
+/// Keeps the first of the ranked `items` in each group, counting the others
+/// in the last element of the kept item.
+fn collapse_groups(
+	index: &Index,
+	items: Vec<(Ranking, usize, u8, usize)>,
+) -> Vec<(Ranking, usize, u8, usize)> {
+	let mut kept: Vec<(Ranking, usize, u8, usize)> = Vec::with_capacity(items.len());
+	let mut positions: HashMap<u32, usize> = HashMap::new();
+	for item in items {
+		match index.attributes.group(item.1) {
+			Some(group) => match positions.get(&group) {
+				Some(&position) => kept[position].3 += 1,
+				None => {
+					positions.insert(group, kept.len());
+					kept.push(item);
+				}
+			},
+			None => kept.push(item),
+		}
+	}
+	kept
+}
+
+/// Splits a query into lowercased words, each with the multiplier given as
+/// `word^2`, plus the whole query without the multipliers. With `patterns`,
+/// words that are patterns are returned apart, parsed as written so that
+/// escapes keep their case.
+fn parse_query(query: &str, patterns: bool) -> (HashMap<String, f32>, Vec<(Pattern, f32)>) {
+	let mut words: HashMap<String, f32> = HashMap::new();
+	let mut pattern_words: HashMap<&str, (Pattern, f32)> = HashMap::new();
+	let mut whole = String::with_capacity(query.len());
+
+	for piece in query.split_inclusive(char::is_whitespace) {
+		let (word, boost) = split_boost(piece.trim_end());
+		whole.push_str(word);
+		whole.push_str(&piece[piece.trim_end().len()..]);
+
+		if patterns && let Some(pattern) = Pattern::parse(word) {
+			let (_, best) = pattern_words.entry(word).or_insert((pattern, boost));
+			*best = best.max(boost);
+		} else if !word.is_empty() {
+			let best = words.entry(word.to_lowercase()).or_insert(boost);
+			*best = best.max(boost);
+		}
+	}
+
+	if !pattern_words.contains_key(whole.as_str()) {
+		words.entry(whole.to_lowercase()).or_insert(1.0);
+	}
+	(words, pattern_words.into_values().collect())
+}
+
+/// Splits `word^2` into the word and its multiplier, which is `1.0` if not
+/// given or not a non-negative number.
+pub(crate) fn split_boost(word: &str) -> (&str, f32) {
+	match word.rsplit_once('^') {
+		Some((term, boost)) if !term.is_empty() => match boost.parse::<f32>() {
+			Ok(boost) if boost.is_finite() && boost >= 0.0 => (term, boost),
+			_ => (word, 1.0),
+		},
+		_ => (word, 1.0),
+	}
+}
+
+/// Scales the weights of `postings` by the query word's `boost` and by the
+/// boost of each posting's field, keeping them sorted by descending weight.
+fn boost_postings<'a>(
+	postings: &'a Postings,
+	fields: &[u8],
+	boost: f32,
+	field_boosts: &[f32],
+) -> Cow<'a, Postings> {
+	let field_boost = |position: usize| {
+		let field = fields.get(position).copied().unwrap_or(0) as usize;
+		field_boosts.get(field).copied().unwrap_or(1.0)
+	};
+	if boost == 1.0 && (fields.is_empty() || field_boosts.iter().all(|&boost| boost == 1.0)) {
+		return Cow::Borrowed(postings);
+	}
+
+	let mut boosted: Postings = postings
+		.iter()
+		.enumerate()
+		.map(|(position, &(item_index, weight))| {
+			let weight = (weight as f32 * boost * field_boost(position)).round();
+			(item_index, weight.clamp(0.0, u8::MAX as f32) as u8)
+		})
+		.collect();
+	boosted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+	Cow::Owned(boosted)
+}
+
+/// Orders by the sort keys first and then by descending boosted score.
+pub(crate) fn compare_rankings(a: &Ranking, b: &Ranking, sort: &[SortKey]) -> Ordering {
+	for ((a, b), key) in a.sort_values.iter().zip(&b.sort_values).zip(sort) {
+		let ordering = match (a, b) {
+			(Some(a), Some(b)) if key.descending => b.total_cmp(a),
+			(Some(a), Some(b)) => a.total_cmp(b),
+			(Some(_), None) => Ordering::Less,
+			(None, Some(_)) => Ordering::Greater,
+			(None, None) => Ordering::Equal,
+		};
+		if ordering != Ordering::Equal {
+			return ordering;
+		}
+	}
+	b.score.total_cmp(&a.score)
+}
+
+/// The items passing every facet and range filter, or `None` if there are
+/// no filters.
+fn filter_items(index: &Index, options: &SearchOptions) -> Option<ItemSet> {
+	let mut allowed: Option<ItemSet> = None;
+	let mut restrict = |passing: ItemSet| match &mut allowed {
+		Some(allowed) => allowed.intersect_with(&passing),
+		None => allowed = Some(passing),
+	};
+
+	for filter in &options.filters {
+		let mut passing = ItemSet::new();
+		if let Some(facet) = index.attributes.facet(&filter.facet) {
+			for value in &filter.values {
+				let position = facet.values.binary_search(value).ok();
+				// The value lists may differ in length in an unverified index.
+				if let Some(items) = position.and_then(|position| facet.items.get(position)) {
+					for &item_index in items {
+						passing.insert(item_index as usize);
+					}
+				}
+			}
+		}
+		restrict(passing);
+	}
+
+	for range in &options.ranges {
+		let mut passing = ItemSet::new();
+		if let Some(column) = index.attributes.number(&range.attribute) {
+			for (item_index, value) in column.values.iter().enumerate() {
+				if value.is_some_and(|value| range.contains(value)) {
+					passing.insert(item_index);
+				}
+			}
+		}
+		restrict(passing);
+	}
+
+	allowed
+}
+
+fn count_facets(index: &Index, names: &[String], matched: &[usize]) -> Vec<FacetCounts> {
+	if names.is_empty() {
+		return Vec::new();
+	}
+	let matched: ItemSet = matched.iter().copied().collect();
+	names
+		.iter()
+		.map(|name| {
+			let mut values: Vec<FacetValueCount> = index
+				.attributes
+				.facet(name)
+				.into_iter()
+				.flat_map(|facet| facet.values.iter().zip(&facet.items))
+				.map(|(value, items)| FacetValueCount {
+					value: value.clone(),
+					count: items
+						.iter()
+						.filter(|&&item_index| matched.contains(item_index as usize))
+						.count(),
+				})
+				.filter(|value| value.count > 0)
+				.collect();
+			values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+			FacetCounts {
+				facet: name.clone(),
+				values,
+			}
+		})
+		.collect()
+}
+
+/// Finds the keywords `query_word` matches by prefix or within edit distance
+/// 1, keeping the exact match and the `max_expansions` most frequent others.
+fn expand(
+	index: &Index,
+	map: &fst::Map<&Vec<u8>>,
+	query_word: &str,
+	options: &SearchOptions,
+	stats: &mut SearchStats,
+) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+	use fst::automaton::Str;
+	use fst::map::OpBuilder;
+	use fst::{Automaton, Streamer};
+
+	let prefix = Str::new(query_word).starts_with();
+	let mut op = OpBuilder::new().add(map.search(prefix));
+	let length = query_word.chars().count();
+	if length >= options.min_fuzzy_length {
+		let max_distance = if length >= options.min_two_typo_length {
+			2
+		} else {
+			1
+		};
+		op = op.add(map.search(EditDistance::new(
+			query_word,
+			max_distance,
+			options.transpositions,
+		)));
+	}
+	let mut matches = op.union();
+
+	let mut exact: Option<u64> = None;
+	// Least frequent expansion on top, ties broken towards the later keyword.
+	let mut expansions: BinaryHeap<Reverse<(usize, Reverse<u64>)>> = BinaryHeap::new();
+
+	while let Some((keyword, indexed_values)) = matches.next() {
+		let Some(indexed_value) = indexed_values.first() else {
+			continue;
+		};
+		let keyword_index = indexed_value.value;
+		stats.matched_keywords += 1;
+
+		if keyword == query_word.as_bytes() {
+			exact = Some(keyword_index);
+			continue;
+		}
+
+		let frequency = index
+			.keyword_to_items
+			.get(keyword_index as usize)
+			.map_or(0, Vec::len);
+		let key = (frequency, Reverse(keyword_index));
+		if expansions.len() < options.max_expansions {
+			expansions.push(Reverse(key));
+		} else if let Some(mut least) = expansions.peek_mut()
+			&& key > least.0
+		{
+			*least = Reverse(key);
+		}
+	}
+
+	Ok(
+		exact
+			.into_iter()
+			.chain(
+				expansions
+					.into_iter()
+					.map(|Reverse((_, Reverse(keyword_index)))| keyword_index),
+			)
+			.collect(),
+	)
+}
+
+/// Keywords matching `pattern`, the most frequent first, at most
+/// `max_expansions` of them.
+fn expand_pattern(
+	index: &Index,
+	map: &fst::Map<&Vec<u8>>,
+	pattern: &Pattern,
+	options: &SearchOptions,
+	stats: &mut SearchStats,
+) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+	use fst::{IntoStreamer, Streamer};
+
+	let mut keyword_indices: Vec<(usize, u64)> = Vec::new();
+	let mut matches = map.search(pattern).into_stream();
+	while let Some((_, keyword_index)) = matches.next() {
+		stats.matched_keywords += 1;
+		let frequency = index
+			.keyword_to_items
+			.get(keyword_index as usize)
+			.map_or(0, Vec::len);
+		keyword_indices.push((frequency, keyword_index));
+	}
+	keyword_indices.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+	keyword_indices.truncate(options.max_expansions);
+	Ok(
+		keyword_indices
+			.into_iter()
+			.map(|(_, keyword_index)| keyword_index)
+			.collect(),
+	)
+}
+
+/// Keywords sounding like `query_word` that `expand` did not already find,
+/// the most frequent first, at most `max_expansions` of them.
+fn expand_phonetic(
+	index: &Index,
+	query_word: &str,
+	expanded: &[u64],
+	options: &SearchOptions,
+) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+	if options.phonetic_weight <= 0.0
+		|| index.phonetic.is_empty()
+		|| query_word.contains(char::is_whitespace)
+		|| query_word.chars().count() < options.min_fuzzy_length
+	{
+		return Ok(Vec::new());
+	}
+
+	let mut keyword_indices: Vec<(usize, u64)> = index
+		.phonetic
+		.keywords(query_word)?
+		.iter()
+		.map(|&keyword_index| keyword_index as u64)
+		.filter(|keyword_index| !expanded.contains(keyword_index))
+		.map(|keyword_index| {
+			let frequency = index
+				.keyword_to_items
+				.get(keyword_index as usize)
+				.map_or(0, Vec::len);
+			(frequency, keyword_index)
+		})
+		.collect();
+	keyword_indices.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+	keyword_indices.truncate(options.max_expansions);
+	Ok(
+		keyword_indices
+			.into_iter()
+			.map(|(_, keyword_index)| keyword_index)
+			.collect(),
+	)
+}
@@ -0,0 +1,193 @@
+use serde::Serialize;
+
+use crate::{Index, Postings};
+
+/// Size and shape of an [`Index`], for finding out what makes it large.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+	pub items: usize,
+	pub keywords: usize,
+	/// Total number of `(item, weight)` postings over all keywords.
+	pub postings: usize,
+	/// Keyword counts by posting list length, in power-of-two buckets.
+	pub posting_histogram: Vec<HistogramBucket>,
+	/// The keywords with the most postings, most frequent first.
+	pub top_keywords: Vec<KeywordStats>,
+	/// Number of symbols in the FSST dictionary used for item IDs.
+	pub id_dictionary_symbols: usize,
+	pub sections: SectionSizes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBucket {
+	/// Smallest posting list length in the bucket.
+	pub min: usize,
+	/// Largest posting list length in the bucket.
+	pub max: usize,
+	pub keywords: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordStats {
+	pub keyword: String,
+	pub postings: usize,
+	pub total_weight: u64,
+}
+
+/// Serialized size in bytes of each part of the index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionSizes {
+	pub fst: usize,
+	pub id_dictionary: usize,
+	pub ids: usize,
+	pub postings: usize,
+	pub keyword_weights: usize,
+	pub phonetic: usize,
+	pub fields: usize,
+	pub attributes: usize,
+	pub total: usize,
+}
+
+/// A keyword with the items it points to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordPostings {
+	pub keyword: String,
+	/// `(item ID, weight)` pairs in ranking order.
+	pub postings: Vec<(String, u8)>,
+}
+
+/// Counts the bytes postcard would produce.
+#[derive(Default)]
+struct SizeCounter(usize);
+
+impl postcard::ser_flavors::Flavor for SizeCounter {
+	type Output = usize;
+
+	fn try_push(&mut self, _data: u8) -> postcard::Result<()> {
+		self.0 += 1;
+		Ok(())
+	}
+
+	fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+		self.0 += data.len();
+		Ok(())
+	}
+
+	fn finalize(self) -> postcard::Result<usize> {
+		Ok(self.0)
+	}
+}
+
+fn serialized_size(value: &impl Serialize) -> Result<usize, Box<dyn std::error::Error>> {
+	Ok(postcard::serialize_with_flavor(
+		value,
+		SizeCounter::default(),
+	)?)
+}
+
+impl Index {
+	/// Collects statistics, listing the `top_n` keywords with most postings.
+	pub fn stats(&self, top_n: usize) -> Result<IndexStats, Box<dyn std::error::Error>> {
+		let mut posting_histogram: Vec<HistogramBucket> = Vec::new();
+		let mut keywords: Vec<KeywordStats> = Vec::with_capacity(self.keyword_count());
+
+		for (keyword, postings) in self.keyword_postings()? {
+			let bucket = postings.len().max(1).ilog2() as usize;
+			while posting_histogram.len() <= bucket {
+				let min = 1 << posting_histogram.len();
+				posting_histogram.push(HistogramBucket {
+					min,
+					max: min * 2 - 1,
+					keywords: 0,
+				});
+			}
+			posting_histogram[bucket].keywords += 1;
+
+			keywords.push(KeywordStats {
+				keyword,
+				postings: postings.len(),
+				total_weight: postings.iter().map(|(_, weight)| *weight as u64).sum(),
+			});
+		}
+
+		keywords.sort_by(|a, b| {
+			b.postings
+				.cmp(&a.postings)
+				.then_with(|| a.keyword.cmp(&b.keyword))
+		});
+		keywords.truncate(top_n);
+
+		let fst = serialized_size(&self.fst)?;
+		let id_dictionary = serialized_size(&(&self.ids.dict_syms, &self.ids.dict_lens))?;
+		let ids = serialized_size(&self.ids)?;
+		let postings = serialized_size(&self.keyword_to_items)?;
+		let keyword_weights = serialized_size(&self.keyword_weights)?;
+		let phonetic = serialized_size(&self.phonetic)?;
+		let fields = serialized_size(&self.fields)?;
+		let attributes = serialized_size(&self.attributes)?;
+
+		Ok(IndexStats {
+			items: self.len(),
+			keywords: self.keyword_count(),
+			postings: self.keyword_to_items.iter().map(Vec::len).sum(),
+			posting_histogram,
+			top_keywords: keywords,
+			id_dictionary_symbols: self.ids.dict_syms.len(),
+			sections: SectionSizes {
+				fst,
+				id_dictionary,
+				ids: ids - id_dictionary,
+				postings,
+				keyword_weights,
+				phonetic,
+				fields,
+				attributes,
+				total: serialized_size(self)?,
+			},
+		})
+	}
+
+	/// Lists every keyword in sorted order with its postings and item IDs.
+	pub fn dump(&self) -> Result<Vec<KeywordPostings>, Box<dyn std::error::Error>> {
+		self
+			.keyword_postings()?
+			.into_iter()
+			.map(|(keyword, postings)| {
+				let postings = postings
+					.iter()
+					.map(|(item_index, weight)| {
+						let id = self
+							.ids
+							.try_get(*item_index)?
+							.ok_or("Failed to get item id")?;
+						Ok((id, *weight))
+					})
+					.collect::<Result<_, Box<dyn std::error::Error>>>()?;
+				Ok(KeywordPostings { keyword, postings })
+			})
+			.collect()
+	}
+
+	fn keyword_postings(&self) -> Result<Vec<(String, &Postings)>, Box<dyn std::error::Error>> {
+		use fst::Streamer;
+
+		let map = fst::Map::new(self.fst.as_slice())?;
+		let mut stream = map.stream();
+		let mut result = Vec::with_capacity(self.keyword_count());
+
+		while let Some((keyword, keyword_index)) = stream.next() {
+			let postings = self
+				.keyword_to_items
+				.get(keyword_index as usize)
+				.ok_or("Keyword index out of bounds")?;
+			result.push((String::from_utf8_lossy(keyword).into_owned(), postings));
+		}
+
+		Ok(result)
+	}
+}
@@ -0,0 +1,241 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+	AttributesBuilder, BuildOptions, FieldIds, FieldPostings, FsstStrVec, Index, InputItem,
+	ItemAttributes, KeywordMapBuilder, item_keywords, renumber_fields,
+};
+
+/// Approximate heap cost of a buffered keyword besides its own bytes.
+const KEYWORD_OVERHEAD: usize = 64;
+const POSTING_SIZE: usize = size_of::<(usize, u8, u8)>();
+
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Builds an index from items added one at a time.
+///
+/// Only the item IDs and the postings buffered since the last spill are kept
+/// in memory. Once the buffer exceeds the memory budget it is written to a
+/// sorted run file, and [`StreamingIndexBuilder::finish`] merges all runs into
+/// the final FST. The result is identical to [`crate::build_index`] over the
+/// same items.
+pub struct StreamingIndexBuilder {
+	options: BuildOptions,
+	ids: Vec<String>,
+	attributes: AttributesBuilder,
+	field_ids: FieldIds,
+	buffer: HashMap<String, FieldPostings>,
+	buffered_bytes: usize,
+	memory_budget: usize,
+	spill_dir: PathBuf,
+	runs: Vec<PathBuf>,
+}
+
+impl Default for StreamingIndexBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl StreamingIndexBuilder {
+	/// Creates a builder that never spills to disk.
+	pub fn new() -> Self {
+		Self::with_memory_budget(usize::MAX, std::env::temp_dir())
+	}
+
+	/// Creates a builder that spills sorted runs to `spill_dir` whenever the
+	/// buffered postings exceed roughly `memory_budget` bytes.
+	pub fn with_memory_budget(memory_budget: usize, spill_dir: impl Into<PathBuf>) -> Self {
+		StreamingIndexBuilder {
+			options: BuildOptions::default(),
+			ids: Vec::new(),
+			attributes: AttributesBuilder::new(),
+			field_ids: FieldIds::new(),
+			buffer: HashMap::new(),
+			buffered_bytes: 0,
+			memory_budget,
+			spill_dir: spill_dir.into(),
+			runs: Vec::new(),
+		}
+	}
+
+	/// Builds the index with `options` instead of the defaults.
+	pub fn with_options(mut self, options: BuildOptions) -> Self {
+		self.options = options;
+		self
+	}
+
+	pub fn add(&mut self, item: InputItem) -> Result<(), Box<dyn std::error::Error>> {
+		let item_index = self.ids.len();
+
+		for (keyword, weight, field) in item_keywords(&item, self.options.keyword_merge) {
+			let field = self.field_ids.id(field)?;
+			match self.buffer.get_mut(&keyword) {
+				Some(item_scores) => item_scores.push((item_index, weight, field)),
+				None => {
+					self.buffered_bytes += keyword.len() + KEYWORD_OVERHEAD;
+					self
+						.buffer
+						.insert(keyword, vec![(item_index, weight, field)]);
+				}
+			}
+			self.buffered_bytes += POSTING_SIZE;
+		}
+		self.attributes.push(ItemAttributes::from(&item));
+		self.ids.push(item.id);
+
+		if self.buffered_bytes > self.memory_budget {
+			self.spill()?;
+		}
+		Ok(())
+	}
+
+	/// Adds one item per non-empty line of newline-delimited JSON, returning
+	/// the number of items added.
+	pub fn add_ndjson(&mut self, reader: impl BufRead) -> Result<usize, Box<dyn std::error::Error>> {
+		let mut count = 0;
+
+		for (line_number, line) in reader.lines().enumerate() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			let item: InputItem = serde_json::from_str(&line)
+				.map_err(|e| format!("Failed to parse line {}: {}", line_number + 1, e))?;
+			self.add(item)?;
+			count += 1;
+		}
+
+		Ok(count)
+	}
+
+	/// Number of items added so far.
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+
+	fn drain_sorted(&mut self) -> Vec<(String, FieldPostings)> {
+		let mut sorted: Vec<(String, FieldPostings)> = self.buffer.drain().collect();
+		sorted.sort_by(|a, b| a.0.cmp(&b.0));
+		self.buffered_bytes = 0;
+		sorted
+	}
+
+	fn spill(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+		let sorted = self.drain_sorted();
+
+		let path = self.spill_dir.join(format!(
+			"docfind-run-{}-{}.bin",
+			std::process::id(),
+			NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+		));
+		self.runs.push(path.clone());
+
+		let mut writer = BufWriter::new(File::create(&path)?);
+		for entry in &sorted {
+			let bytes = postcard::to_allocvec(entry)?;
+			writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+			writer.write_all(&bytes)?;
+		}
+		writer.flush()?;
+
+		Ok(())
+	}
+
+	pub fn finish(mut self) -> Result<Index, Box<dyn std::error::Error>> {
+		let mut runs: Vec<Run> = Vec::with_capacity(self.runs.len() + 1);
+		for path in &self.runs {
+			runs.push(Run::File(BufReader::new(File::open(path)?)));
+		}
+		runs.push(Run::Memory(self.drain_sorted().into_iter()));
+
+		let mut heads: Vec<FieldPostings> = vec![Vec::new(); runs.len()];
+		let mut heap: BinaryHeap<Reverse<(String, usize)>> = BinaryHeap::new();
+		for (run_index, run) in runs.iter_mut().enumerate() {
+			if let Some((keyword, item_scores)) = run.next_entry()? {
+				heads[run_index] = item_scores;
+				heap.push(Reverse((keyword, run_index)));
+			}
+		}
+
+		let (field_names, field_remap) = std::mem::take(&mut self.field_ids).finish();
+		let mut keyword_map = KeywordMapBuilder::new();
+		while let Some(Reverse((keyword, run_index))) = heap.pop() {
+			let mut item_scores = std::mem::take(&mut heads[run_index]);
+			if let Some((next, next_scores)) = runs[run_index].next_entry()? {
+				heads[run_index] = next_scores;
+				heap.push(Reverse((next, run_index)));
+			}
+
+			while let Some(Reverse((next, _))) = heap.peek()
+				&& *next == keyword
+			{
+				let Some(Reverse((_, other))) = heap.pop() else {
+					break;
+				};
+				item_scores.append(&mut heads[other]);
+				if let Some((next, next_scores)) = runs[other].next_entry()? {
+					heads[other] = next_scores;
+					heap.push(Reverse((next, other)));
+				}
+			}
+
+			keyword_map.insert(keyword, renumber_fields(item_scores, &field_remap))?;
+		}
+
+		let (fst, keyword_to_items, fields) = keyword_map.finish(field_names)?;
+		let ids = std::mem::take(&mut self.ids);
+		let attributes = std::mem::take(&mut self.attributes).finish();
+
+		Index::new(
+			fst,
+			FsstStrVec::from_strings(&ids),
+			keyword_to_items,
+			fields,
+			attributes,
+			self.options,
+		)
+	}
+}
+
+impl Drop for StreamingIndexBuilder {
+	fn drop(&mut self) {
+		for path in &self.runs {
+			let _ = std::fs::remove_file(path);
+		}
+	}
+}
+
+enum Run {
+	File(BufReader<File>),
+	Memory(std::vec::IntoIter<(String, FieldPostings)>),
+}
+
+impl Run {
+	fn next_entry(&mut self) -> Result<Option<(String, FieldPostings)>, Box<dyn std::error::Error>> {
+		match self {
+			Run::Memory(entries) => Ok(entries.next()),
+			Run::File(reader) => {
+				let mut len = [0u8; 4];
+				match reader.read_exact(&mut len) {
+					Ok(()) => {}
+					Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+					Err(e) => return Err(e.into()),
+				}
+
+				let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+				reader.read_exact(&mut bytes)?;
+				Ok(Some(postcard::from_bytes(&bytes)?))
+			}
+		}
+	}
+}
@@ -0,0 +1,278 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use fst::automaton::Str;
+use fst::{Automaton, IntoStreamer, Streamer};
+use serde::{Deserialize, Serialize};
+
+use crate::attributes::ItemSet;
+use crate::{Index, Postings};
+
+/// A completion of the last word of a query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+	/// The query with its last word replaced by the completion.
+	pub text: String,
+	/// The keyword the last word was completed to.
+	pub keyword: String,
+	/// Number of items containing the keyword, or, with earlier words, the
+	/// number of those also containing every earlier word.
+	pub frequency: usize,
+	/// Sum of the keyword's weights over the same items.
+	pub weight: u64,
+}
+
+/// Controls how many suggestions are returned and how they are ranked.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SuggestOptions {
+	pub max_suggestions: usize,
+	pub rank: SuggestRank,
+}
+
+impl Default for SuggestOptions {
+	fn default() -> Self {
+		SuggestOptions {
+			max_suggestions: 10,
+			rank: SuggestRank::default(),
+		}
+	}
+}
+
+/// What suggestions are ranked by. Ties are broken by the other measure,
+/// then alphabetically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SuggestRank {
+	/// [`Suggestion::frequency`].
+	#[default]
+	Frequency,
+	/// [`Suggestion::weight`].
+	Weight,
+}
+
+/// Sums the weights of each posting list, so that unfiltered suggestions do
+/// not have to walk the postings of every completion.
+pub(crate) fn keyword_weights(keyword_to_items: &[Postings]) -> Vec<u64> {
+	keyword_to_items
+		.iter()
+		.map(|postings| postings.iter().map(|(_, weight)| *weight as u64).sum())
+		.collect()
+}
+
+/// Suggests up to `max_suggestions` completions of the last word of `query`,
+/// ranked by frequency.
+pub fn suggest(
+	index: &Index,
+	query: &str,
+	max_suggestions: usize,
+) -> Result<Vec<Suggestion>, Box<dyn std::error::Error>> {
+	let options = SuggestOptions {
+		max_suggestions,
+		..Default::default()
+	};
+	suggest_with_options(index, query, &options)
+}
+
+/// Suggests completions of the last word of `query` among the keywords
+/// starting with it.
+///
+/// Earlier words must each match a keyword exactly, and only items containing
+/// all of them count towards the frequency and weight of a completion, so
+/// `"rust pro"` suggests words that appear alongside `rust`. A query ending in
+/// whitespace has no last word to complete and gets no suggestions.
+pub fn suggest_with_options(
+	index: &Index,
+	query: &str,
+	options: &SuggestOptions,
+) -> Result<Vec<Suggestion>, Box<dyn std::error::Error>> {
+	let mut top = TopCompletions::new(options);
+	completions(index, query, &mut top)?;
+	Ok(top.into_suggestions(query))
+}
+
+/// Receives the completions of a query word, in keyword order.
+pub(crate) trait Completions {
+	/// Whether a completion with at most `frequency` and `weight` could still
+	/// be kept, so that hopeless ones are not counted.
+	fn wants(&self, _frequency: usize, _weight: u64) -> bool {
+		true
+	}
+
+	fn add(&mut self, keyword: &str, frequency: usize, weight: u64);
+}
+
+/// Completions summed over several indexes, by keyword.
+impl Completions for HashMap<String, (usize, u64)> {
+	fn add(&mut self, keyword: &str, frequency: usize, weight: u64) {
+		let (total_frequency, total_weight) = self.entry(keyword.to_string()).or_default();
+		*total_frequency += frequency;
+		*total_weight += weight;
+	}
+}
+
+/// Passes every completion of the last word of `query` to `sink`, in
+/// keyword order.
+pub(crate) fn completions(
+	index: &Index,
+	query: &str,
+	sink: &mut impl Completions,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let Some((head, last)) = split_last_word(query) else {
+		return Ok(());
+	};
+	let prefix = last.to_lowercase();
+
+	let map = fst::Map::new(&index.fst)?;
+	let earlier: HashSet<String> = head.split_whitespace().map(str::to_lowercase).collect();
+	let mut context: Option<ItemSet> = None;
+	for word in &earlier {
+		let Some(keyword_index) = map.get(word) else {
+			return Ok(());
+		};
+		let items: ItemSet = postings(index, keyword_index)?
+			.iter()
+			.map(|&(item_index, _)| item_index)
+			.collect();
+		match &mut context {
+			Some(context) => context.intersect_with(&items),
+			None => context = Some(items),
+		}
+	}
+
+	let matcher = Str::new(&prefix).starts_with();
+	let mut stream = map.search(matcher).into_stream();
+	while let Some((keyword, keyword_index)) = stream.next() {
+		let keyword = std::str::from_utf8(keyword)?;
+		if earlier.contains(keyword) {
+			continue;
+		}
+		let postings = postings(index, keyword_index)?;
+		let total_weight = index
+			.keyword_weights
+			.get(keyword_index as usize)
+			.copied()
+			.unwrap_or_default();
+		// Earlier words can only lower the counts, so skip the walk over the
+		// postings when even the unfiltered counts fall short.
+		if !sink.wants(postings.len(), total_weight) {
+			continue;
+		}
+		let (frequency, weight) = match &context {
+			Some(context) => postings
+				.iter()
+				.filter(|(item_index, _)| context.contains(*item_index))
+				.fold((0, 0), |(frequency, weight), (_, item_weight)| {
+					(frequency + 1, weight + *item_weight as u64)
+				}),
+			None => (postings.len(), total_weight),
+		};
+		if frequency > 0 {
+			sink.add(keyword, frequency, weight);
+		}
+	}
+	Ok(())
+}
+
+/// Splits `query` before its last word, or returns nothing if it has no
+/// last word to complete.
+fn split_last_word(query: &str) -> Option<(&str, &str)> {
+	if query.ends_with(char::is_whitespace) {
+		return None;
+	}
+	let last = query.split_whitespace().last()?;
+	Some(query.split_at(query.len() - last.len()))
+}
+
+/// The best `max_suggestions` completions seen so far.
+pub(crate) struct TopCompletions {
+	rank: SuggestRank,
+	max_suggestions: usize,
+	/// The worst kept completion on top.
+	heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+/// A completion ordered by its rank, then alphabetically, so that better
+/// completions compare greater.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Ranked {
+	key: (u64, u64),
+	keyword: Reverse<String>,
+	frequency: usize,
+	weight: u64,
+}
+
+impl TopCompletions {
+	pub fn new(options: &SuggestOptions) -> Self {
+		TopCompletions {
+			rank: options.rank,
+			max_suggestions: options.max_suggestions,
+			heap: BinaryHeap::new(),
+		}
+	}
+
+	fn key(&self, frequency: usize, weight: u64) -> (u64, u64) {
+		match self.rank {
+			SuggestRank::Frequency => (frequency as u64, weight),
+			SuggestRank::Weight => (weight, frequency as u64),
+		}
+	}
+
+	/// The completions kept, best first, with the last word of `query`
+	/// replaced by each.
+	pub fn into_suggestions(self, query: &str) -> Vec<Suggestion> {
+		let head = split_last_word(query).map_or("", |(head, _)| head);
+		self
+			.heap
+			.into_sorted_vec()
+			.into_iter()
+			.map(|Reverse(ranked)| Suggestion {
+				text: format!("{}{}", head, ranked.keyword.0),
+				keyword: ranked.keyword.0,
+				frequency: ranked.frequency,
+				weight: ranked.weight,
+			})
+			.collect()
+	}
+}
+
+impl Completions for TopCompletions {
+	/// Completions come in keyword order, so a later one equal in rank to the
+	/// worst kept completion loses to it.
+	fn wants(&self, frequency: usize, weight: u64) -> bool {
+		if self.heap.len() < self.max_suggestions {
+			return true;
+		}
+		self
+			.heap
+			.peek()
+			.is_some_and(|Reverse(worst)| self.key(frequency, weight) > worst.key)
+	}
+
+	fn add(&mut self, keyword: &str, frequency: usize, weight: u64) {
+		let key = self.key(frequency, weight);
+		if self.heap.len() >= self.max_suggestions {
+			let Some(Reverse(worst)) = self.heap.peek() else {
+				return;
+			};
+			if (key, Reverse(keyword)) <= (worst.key, Reverse(worst.keyword.0.as_str())) {
+				return;
+			}
+			self.heap.pop();
+		}
+		self.heap.push(Reverse(Ranked {
+			key,
+			keyword: Reverse(keyword.to_string()),
+			frequency,
+			weight,
+		}));
+	}
+}
+
+fn postings(index: &Index, keyword_index: u64) -> Result<&Postings, Box<dyn std::error::Error>> {
+	index
+		.keyword_to_items
+		.get(keyword_index as usize)
+		.ok_or_else(|| "Keyword index out of bounds".into())
+}
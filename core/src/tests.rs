@@ -1,7 +1,13 @@
+#![allow(clippy::module_inception)]
+
 mod tests {
-	use crate::Index;
+	use crate::{DuplicatePolicy, Index, IndexWriter, MultiIndex, SearchHit, SearchOptions, StreamingIndexBuilder};
 	use crate::{InputItem, SearchTokens, FsstStrVec};
-	use crate::{build_index, search};
+	use crate::{BoostMode, FacetFilter, FacetValueCount, RangeFilter, SortKey};
+	use crate::{BuildOptions, KeywordMerge, build_index_with_options};
+	use crate::{build_index, search, search_hits, search_with_options};
+	use crate::{SuggestOptions, SuggestRank, suggest, suggest_with_options};
+	use std::collections::BTreeMap;
 
 	#[test]
 	fn test_fsst_str_vec_basic() {
@@ -78,6 +84,7 @@ mod tests {
 				(SearchTokens::Raw("hello world".to_string()), 100),
 				(SearchTokens::Tokens(vec!["tag1".to_string(), "tag2".to_string()]), 50),
 			],
+			..Default::default()
 		};
 
 		assert_eq!(item.id, "item-001");
@@ -122,12 +129,14 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("rust programming".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("python guide".to_string()), 90),
 				],
+				..Default::default()
 			},
 		];
 
@@ -155,6 +164,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("test query".to_string()), 100),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items);
@@ -173,6 +183,7 @@ mod tests {
 					(SearchTokens::Raw("important".to_string()), 100),
 					(SearchTokens::Raw("secondary".to_string()), 50),
 				],
+				..Default::default()
 			},
 		];
 
@@ -187,6 +198,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("test".to_string()), 100),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -209,12 +221,14 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("first item".to_string()), 100),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("second item".to_string()), 100),
 				],
+				..Default::default()
 			},
 		];
 
@@ -237,12 +251,14 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("rust programming".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("python guide".to_string()), 90),
 				],
+				..Default::default()
 			},
 		];
 
@@ -260,6 +276,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("JavaScript Tutorial".to_string()), 90),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -284,6 +301,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("rust programming".to_string()), 90),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -299,6 +317,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("test".to_string()), 100),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -315,18 +334,21 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("wireless audio".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("wireless mouse".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-003".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("python book".to_string()), 90),
 				],
+				..Default::default()
 			},
 		];
 
@@ -345,6 +367,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("debugging tools".to_string()), 90),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -361,24 +384,28 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("product one".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("product two".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-003".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("product three".to_string()), 90),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-004".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("product four".to_string()), 90),
 				],
+				..Default::default()
 			},
 		];
 
@@ -401,12 +428,14 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Tokens(vec!["tag1".to_string(), "tag2".to_string()]), 100),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Tokens(vec!["tag2".to_string(), "tag3".to_string()]), 100),
 				],
+				..Default::default()
 			},
 		];
 
@@ -434,11 +463,14 @@ mod tests {
 		fst_builder.insert("electronics", 2).unwrap();
 		let fst = fst_builder.into_inner()?;
 
-		let index = Index {
+		let index = Index::new(
 			fst,
-			ids: ids_fsst,
+			ids_fsst,
 			keyword_to_items,
-		};
+			crate::fields::Fields::default(),
+			crate::attributes::Attributes::default(),
+			crate::BuildOptions::default(),
+		)?;
 
 		let results = search(&index, "audiio", 10)?;
 		assert_eq!(results.len(), 1, "Expected 1 result for 'audiio'");
@@ -455,12 +487,14 @@ mod tests {
 					(SearchTokens::Raw("rust".to_string()), 50),
 					(SearchTokens::Raw("programming".to_string()), 50),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("rust".to_string()), 100),
 				],
+				..Default::default()
 			},
 		];
 
@@ -479,12 +513,14 @@ mod tests {
 				search_terms: vec![
 					(SearchTokens::Raw("keyword".to_string()), 100),
 				],
+				..Default::default()
 			},
 			InputItem {
 				id: "item-002".to_string(),
 				search_terms: vec![
 					(SearchTokens::Raw("keyword".to_string()), 50),
 				],
+				..Default::default()
 			},
 		];
 
@@ -503,6 +539,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("test".to_string()), 100),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -519,6 +556,7 @@ mod tests {
 			search_terms: vec![
 				(SearchTokens::Raw("test test duplicate".to_string()), 100),
 			],
+			..Default::default()
 		}];
 
 		let index = build_index(items).unwrap();
@@ -526,4 +564,1886 @@ mod tests {
 
 		assert!(!results.is_empty());
 	}
+
+	fn repeated_keyword_item(id: &str) -> InputItem {
+		InputItem {
+			id: id.to_string(),
+			search_terms: vec![
+				(SearchTokens::Raw("book book guide".to_string()), 40),
+				(SearchTokens::Tokens(vec!["book".to_string()]), 60),
+			],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_keyword_merge_policies() {
+		let score = |keyword_merge: KeywordMerge, query: &str| {
+			let options = BuildOptions {
+				keyword_merge,
+				..Default::default()
+			};
+			let items = vec![repeated_keyword_item("item-001")];
+			let index = build_index_with_options(items, options).unwrap();
+			let hits = search_hits(&index, query, 10).unwrap();
+			assert_eq!(hits.len(), 1);
+			hits[0].score
+		};
+
+		assert_eq!(score(KeywordMerge::First, "book"), 40);
+		assert_eq!(score(KeywordMerge::Max, "book"), 60);
+		assert_eq!(score(KeywordMerge::Sum, "book"), 140);
+		// 60 * (1 + ln 3)
+		assert_eq!(score(KeywordMerge::WeightedByCount, "book"), 126);
+		let policies = [
+			KeywordMerge::First,
+			KeywordMerge::Max,
+			KeywordMerge::Sum,
+			KeywordMerge::WeightedByCount,
+		];
+		for policy in policies {
+			assert_eq!(score(policy, "guide"), 40);
+		}
+
+		let sum_item = InputItem {
+			id: "item-001".to_string(),
+			search_terms: vec![(SearchTokens::Raw("book book book".to_string()), 100)],
+			..Default::default()
+		};
+		let options = BuildOptions {
+			keyword_merge: KeywordMerge::Sum,
+			..Default::default()
+		};
+		let index = build_index_with_options(vec![sum_item], options).unwrap();
+		assert_eq!(search_hits(&index, "book", 10).unwrap()[0].score, 255);
+	}
+
+	#[test]
+	fn test_keyword_merge_is_kept_by_writer_and_stream() {
+		let options = BuildOptions {
+			keyword_merge: KeywordMerge::Max,
+			..Default::default()
+		};
+		let expected = build_index_with_options(
+			vec![
+				repeated_keyword_item("item-001"),
+				repeated_keyword_item("item-002"),
+			],
+			options,
+		)
+		.unwrap();
+		assert_eq!(expected.build_options(), &options);
+
+		let base = build_index_with_options(vec![repeated_keyword_item("item-001")], options).unwrap();
+		let mut writer = IndexWriter::open(base).unwrap();
+		writer.add(repeated_keyword_item("item-002")).unwrap();
+		assert_eq!(
+			writer.finish().unwrap().to_bytes().unwrap(),
+			expected.to_bytes().unwrap()
+		);
+
+		let mut builder = StreamingIndexBuilder::new().with_options(options);
+		builder.add(repeated_keyword_item("item-001")).unwrap();
+		builder.add(repeated_keyword_item("item-002")).unwrap();
+		assert_eq!(
+			builder.finish().unwrap().to_bytes().unwrap(),
+			expected.to_bytes().unwrap()
+		);
+	}
+
+	fn item(id: &str, raw: &str, weight: u8) -> InputItem {
+		InputItem {
+			id: id.to_string(),
+			search_terms: vec![(SearchTokens::Raw(raw.to_string()), weight)],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_index_writer_matches_full_rebuild() {
+		let base = build_index(vec![
+			item("item-001", "rust programming", 90),
+			item("item-002", "python guide", 90),
+			item("item-003", "javascript tutorial", 90),
+		])
+		.unwrap();
+
+		let mut writer = IndexWriter::open(base).unwrap();
+		assert!(writer.delete("item-002"));
+		writer
+			.update(item("item-003", "typescript handbook", 90))
+			.unwrap();
+		writer.add(item("item-004", "rust cookbook", 90)).unwrap();
+		assert_eq!(writer.len(), 3);
+
+		let patched = writer.finish().unwrap();
+		let rebuilt = build_index(vec![
+			item("item-001", "rust programming", 90),
+			item("item-003", "typescript handbook", 90),
+			item("item-004", "rust cookbook", 90),
+		])
+		.unwrap();
+
+		assert_eq!(patched.to_bytes().unwrap(), rebuilt.to_bytes().unwrap());
+	}
+
+	#[test]
+	fn test_index_writer_search_after_patch() {
+		let base = build_index(vec![
+			item("item-001", "wireless audio", 90),
+			item("item-002", "wireless mouse", 90),
+		])
+		.unwrap();
+
+		let mut writer = IndexWriter::open(base).unwrap();
+		writer.delete("item-001");
+		writer.upsert(item("item-002", "bluetooth keyboard", 90));
+		writer.upsert(item("item-003", "wireless headphones", 90));
+		let index = writer.finish().unwrap();
+
+		assert_eq!(search(&index, "wireless", 10).unwrap(), vec!["item-003"]);
+		assert_eq!(search(&index, "keyboard", 10).unwrap(), vec!["item-002"]);
+		assert!(search(&index, "audio", 10).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_index_writer_rejects_invalid_operations() {
+		let base = build_index(vec![item("item-001", "test", 90)]).unwrap();
+
+		let mut writer = IndexWriter::open(base).unwrap();
+		assert!(writer.add(item("item-001", "duplicate", 90)).is_err());
+		assert!(writer.update(item("item-002", "missing", 90)).is_err());
+		assert!(!writer.delete("item-002"));
+
+		assert!(writer.delete("item-001"));
+		let index = writer.finish().unwrap();
+		assert!(index.is_empty());
+		assert!(search(&index, "test", 10).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_merge_matches_combined_build() {
+		let first = build_index(vec![
+			item("item-001", "rust programming", 90),
+			item("item-002", "python guide", 90),
+		])
+		.unwrap();
+		let second = build_index(vec![
+			item("item-003", "rust cookbook", 90),
+			item("item-004", "javascript tutorial", 90),
+		])
+		.unwrap();
+
+		let merged = Index::merge(&[first, second], DuplicatePolicy::Error).unwrap();
+		let combined = build_index(vec![
+			item("item-001", "rust programming", 90),
+			item("item-002", "python guide", 90),
+			item("item-003", "rust cookbook", 90),
+			item("item-004", "javascript tutorial", 90),
+		])
+		.unwrap();
+
+		assert_eq!(merged.to_bytes().unwrap(), combined.to_bytes().unwrap());
+		assert_eq!(
+			search(&merged, "rust", 10).unwrap(),
+			vec!["item-001", "item-003"]
+		);
+	}
+
+	#[test]
+	fn test_merge_duplicate_policies() {
+		let build = || {
+			vec![
+				build_index(vec![
+					item("shared", "old version", 90),
+					item("a", "alpha", 90),
+				])
+				.unwrap(),
+				build_index(vec![
+					item("shared", "new version", 90),
+					item("b", "beta", 90),
+				])
+				.unwrap(),
+			]
+		};
+
+		let first = Index::merge(&build(), DuplicatePolicy::KeepFirst).unwrap();
+		assert_eq!(first.len(), 3);
+		assert_eq!(search(&first, "old", 10).unwrap(), vec!["shared"]);
+		assert!(search(&first, "new", 10).unwrap().is_empty());
+
+		let last = Index::merge(&build(), DuplicatePolicy::KeepLast).unwrap();
+		assert_eq!(last.len(), 3);
+		assert_eq!(search(&last, "new", 10).unwrap(), vec!["shared"]);
+		assert!(search(&last, "old", 10).unwrap().is_empty());
+
+		assert!(Index::merge(&build(), DuplicatePolicy::Error).is_err());
+	}
+
+	#[test]
+	fn test_merge_empty() {
+		let merged = Index::merge(&[], DuplicatePolicy::KeepFirst).unwrap();
+		assert!(merged.is_empty());
+		assert!(search(&merged, "anything", 10).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_search_hits_scores() {
+		let items = vec![
+			InputItem {
+				id: "item-001".to_string(),
+				search_terms: vec![(SearchTokens::Raw("keyword".to_string()), 100)],
+				..Default::default()
+			},
+			InputItem {
+				id: "item-002".to_string(),
+				search_terms: vec![(SearchTokens::Raw("keyword".to_string()), 50)],
+				..Default::default()
+			},
+		];
+
+		let index = build_index(items).unwrap();
+		let hits = search_hits(&index, "keyword", 10).unwrap();
+
+		assert_eq!(
+			hits,
+			vec![
+				SearchHit {
+					id: "item-001".to_string(),
+					score: 100,
+					collapsed: 0
+				},
+				SearchHit {
+					id: "item-002".to_string(),
+					score: 50,
+					collapsed: 0
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_multi_index_merges_by_score() {
+		let docs_v1 = build_index(vec![
+			InputItem {
+				id: "install".to_string(),
+				search_terms: vec![(SearchTokens::Raw("install guide".to_string()), 60)],
+				..Default::default()
+			},
+			InputItem {
+				id: "shared".to_string(),
+				search_terms: vec![(SearchTokens::Raw("guide".to_string()), 90)],
+				..Default::default()
+			},
+		])
+		.unwrap();
+		let docs_v2 = build_index(vec![
+			InputItem {
+				id: "shared".to_string(),
+				search_terms: vec![(SearchTokens::Raw("guide".to_string()), 90)],
+				..Default::default()
+			},
+			InputItem {
+				id: "upgrade".to_string(),
+				search_terms: vec![(SearchTokens::Raw("upgrade guide".to_string()), 100)],
+				..Default::default()
+			},
+		])
+		.unwrap();
+
+		let mut multi = MultiIndex::new();
+		multi.add("v1", docs_v1);
+		multi.add("v2", docs_v2);
+
+		let hits = multi.search("guide", 10).unwrap();
+		let tagged: Vec<(&str, &str, u8)> = hits
+			.iter()
+			.map(|hit| (hit.source.as_str(), hit.id.as_str(), hit.score))
+			.collect();
+
+		assert_eq!(
+			tagged,
+			vec![
+				("v2", "upgrade", 100),
+				("v1", "shared", 90),
+				("v2", "shared", 90),
+				("v1", "install", 60),
+			]
+		);
+
+		assert_eq!(multi.search("guide", 2).unwrap().len(), 2);
+	}
+
+	#[test]
+	fn test_multi_index_add_replace_remove() {
+		let mut multi = MultiIndex::new();
+		multi.add("docs", build_index(vec![item("old", "alpha", 90)]).unwrap());
+		multi.add("docs", build_index(vec![item("new", "alpha", 90)]).unwrap());
+
+		assert_eq!(multi.len(), 1);
+		assert_eq!(multi.search("alpha", 10).unwrap()[0].id, "new");
+
+		assert!(multi.remove("docs").is_some());
+		assert!(multi.is_empty());
+		assert!(multi.search("alpha", 10).unwrap().is_empty());
+	}
+
+	fn sample_items(count: usize) -> Vec<InputItem> {
+		let words = [
+			"rust", "python", "guide", "book", "audio", "wireless", "mouse", "web",
+		];
+		(0..count)
+			.map(|i| InputItem {
+				id: format!("item-{:04}", i),
+				search_terms: vec![
+					(
+						SearchTokens::Raw(format!("{} {} {}", words[i % 8], words[(i / 8) % 8], i)),
+						90,
+					),
+					(
+						SearchTokens::Tokens(vec![words[(i * 3) % 8].to_string()]),
+						(i % 100) as u8,
+					),
+				],
+				..Default::default()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_streaming_builder_matches_build_index() {
+		// Named like the builder's runs so that concurrent test runs and files
+		// left by an aborted run cannot change the counts below.
+		let spill_dir = std::env::temp_dir().join(format!(
+			"docfind-test-streaming-spill-{}",
+			std::process::id()
+		));
+		let _ = std::fs::remove_dir_all(&spill_dir);
+		std::fs::create_dir_all(&spill_dir).unwrap();
+
+		let mut builder = StreamingIndexBuilder::with_memory_budget(512, &spill_dir);
+		for item in sample_items(200) {
+			builder.add(item).unwrap();
+		}
+		assert_eq!(builder.len(), 200);
+		assert!(std::fs::read_dir(&spill_dir).unwrap().count() > 1);
+
+		let streamed = builder.finish().unwrap();
+		let built = build_index(sample_items(200)).unwrap();
+
+		assert_eq!(streamed.to_bytes().unwrap(), built.to_bytes().unwrap());
+		assert_eq!(std::fs::read_dir(&spill_dir).unwrap().count(), 0);
+		std::fs::remove_dir(&spill_dir).unwrap();
+	}
+
+	#[test]
+	fn test_streaming_builder_ndjson() {
+		let ndjson = r#"{"id":"a","searchTerms":[{"type":"raw","value":"rust guide","weight":90}]}
+
+{"id":"b","searchTerms":[{"type":"tokens","value":["python","guide"],"weight":100}]}
+"#;
+
+		let mut builder = StreamingIndexBuilder::new();
+		assert_eq!(builder.add_ndjson(ndjson.as_bytes()).unwrap(), 2);
+
+		let index = builder.finish().unwrap();
+		assert_eq!(search(&index, "guide", 10).unwrap(), vec!["b", "a"]);
+	}
+
+	#[test]
+	fn test_streaming_builder_ndjson_reports_line() {
+		let ndjson = "{\"id\":\"a\",\"searchTerms\":[]}\nnot json\n";
+
+		let mut builder = StreamingIndexBuilder::new();
+		let error = builder.add_ndjson(ndjson.as_bytes()).unwrap_err();
+		assert!(error.to_string().contains("line 2"));
+	}
+
+	#[test]
+	fn test_build_index_is_deterministic() {
+		let first = build_index(sample_items(2000)).unwrap();
+		let second = build_index(sample_items(2000)).unwrap();
+
+		assert_eq!(first.to_bytes().unwrap(), second.to_bytes().unwrap());
+		assert_eq!(first.content_hash(), second.content_hash());
+	}
+
+	#[test]
+	fn test_content_hash_is_platform_independent() {
+		// Golden value: any change to the index format, the FSST training
+		// sample or the hash itself must update this constant deliberately.
+		let index = build_index(sample_items(2000)).unwrap();
+		assert_eq!(index.content_hash(), 0x88a7_55b2_0a07_f831);
+	}
+
+	#[test]
+	fn test_content_hash_survives_roundtrip() {
+		let index = build_index(sample_items(50)).unwrap();
+		let other = build_index(sample_items(51)).unwrap();
+		assert_ne!(index.content_hash(), other.content_hash());
+
+		let deserialized = Index::from_bytes(&index.to_bytes().unwrap()).unwrap();
+		assert_eq!(deserialized.content_hash(), index.content_hash());
+		assert_eq!(deserialized.compute_content_hash(), index.content_hash());
+	}
+
+	#[test]
+	fn test_index_stats() {
+		let items = vec![
+			InputItem {
+				id: "item-001".to_string(),
+				search_terms: vec![(SearchTokens::Raw("wireless audio".to_string()), 90)],
+				..Default::default()
+			},
+			InputItem {
+				id: "item-002".to_string(),
+				search_terms: vec![(SearchTokens::Raw("wireless mouse".to_string()), 80)],
+				..Default::default()
+			},
+			InputItem {
+				id: "item-003".to_string(),
+				search_terms: vec![(SearchTokens::Raw("wireless keyboard mouse".to_string()), 70)],
+				..Default::default()
+			},
+		];
+
+		let index = build_index(items).unwrap();
+		let stats = index.stats(2).unwrap();
+
+		assert_eq!(stats.items, 3);
+		assert_eq!(stats.keywords, 4);
+		assert_eq!(stats.postings, 7);
+
+		let buckets: Vec<(usize, usize, usize)> = stats
+			.posting_histogram
+			.iter()
+			.map(|bucket| (bucket.min, bucket.max, bucket.keywords))
+			.collect();
+		assert_eq!(buckets, vec![(1, 1, 2), (2, 3, 2)]);
+
+		assert_eq!(stats.top_keywords[0].keyword, "wireless");
+		assert_eq!(stats.top_keywords[0].postings, 3);
+		assert_eq!(stats.top_keywords[0].total_weight, 240);
+		assert_eq!(stats.top_keywords[1].keyword, "mouse");
+		assert_eq!(stats.top_keywords.len(), 2);
+
+		let sections = &stats.sections;
+		assert_eq!(sections.total, index.to_bytes().unwrap().len());
+		assert!(
+			sections.fst + sections.id_dictionary + sections.ids + sections.postings < sections.total
+		);
+	}
+
+	#[test]
+	fn test_index_dump() {
+		let items = vec![
+			InputItem {
+				id: "a".to_string(),
+				search_terms: vec![(SearchTokens::Raw("rust guide".to_string()), 50)],
+				..Default::default()
+			},
+			InputItem {
+				id: "b".to_string(),
+				search_terms: vec![(SearchTokens::Raw("guide".to_string()), 90)],
+				..Default::default()
+			},
+		];
+
+		let index = build_index(items).unwrap();
+		let dump = index.dump().unwrap();
+
+		assert_eq!(dump.len(), 2);
+		assert_eq!(dump[0].keyword, "guide");
+		assert_eq!(
+			dump[0].postings,
+			vec![("b".to_string(), 90), ("a".to_string(), 50)]
+		);
+		assert_eq!(dump[1].keyword, "rust");
+		assert_eq!(dump[1].postings, vec![("a".to_string(), 50)]);
+	}
+
+	#[test]
+	fn test_verify_accepts_built_index() {
+		let index = build_index(sample_items(200)).unwrap();
+		index.verify().unwrap();
+
+		let bytes = index.to_bytes().unwrap();
+		let loaded = Index::from_bytes_verified(&bytes).unwrap();
+		assert_eq!(loaded.content_hash(), index.content_hash());
+	}
+
+	#[test]
+	fn test_verify_rejects_corruption() {
+		let verify_err = |index: &Index| index.verify().unwrap_err().to_string();
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		index.keyword_to_items[0].push((20, 10));
+		assert!(verify_err(&index).contains("missing item 20"));
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		index.keyword_to_items.pop();
+		assert!(verify_err(&index).contains("posting lists"));
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		let last = index.fst.len() - 10;
+		index.fst[last] ^= 0xff;
+		assert!(verify_err(&index).contains("FST"));
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		index.ids.data.push(fsst::ESCAPE_CODE);
+		assert!(verify_err(&index).contains("invalid FSST codes"));
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		index.ids.data.extend_from_slice(&[fsst::ESCAPE_CODE, 0xff]);
+		assert!(verify_err(&index).contains("not valid UTF-8"));
+
+		let mut index = build_index(sample_items(20)).unwrap();
+		index.content_hash ^= 1;
+		assert!(verify_err(&index).contains("Content hash"));
+	}
+
+	#[test]
+	fn test_from_bytes_verified_rejects_truncated_bytes() {
+		let bytes = build_index(sample_items(20)).unwrap().to_bytes().unwrap();
+		for len in [0, bytes.len() / 2, bytes.len() - 1] {
+			assert!(Index::from_bytes_verified(&bytes[..len]).is_err());
+		}
+	}
+
+	#[test]
+	fn test_corrupted_bytes_never_panic() {
+		let items = vec![
+			InputItem {
+				facets: [("lang".to_string(), vec!["en".to_string()])].into(),
+				numbers: [("pages".to_string(), 120.0)].into(),
+				boost: Some(2.0),
+				group: Some("/guide".to_string()),
+				..item("doc-1", "rust programming guide", 90)
+			},
+			InputItem {
+				group: Some("/guide".to_string()),
+				..item("doc-2", "python programming", 90)
+			},
+			item("doc-3", "écriture rapide", 90),
+		];
+		let bytes = build_index(items).unwrap().to_bytes().unwrap();
+		let options = SearchOptions {
+			filters: vec![filter("lang", &["en"])],
+			facets: vec!["lang".to_string()],
+			ranges: vec![RangeFilter {
+				attribute: "pages".to_string(),
+				min: Some(100.0),
+				max: None,
+			}],
+			sort: vec![sort_key("pages", true)],
+			collapse: true,
+			..Default::default()
+		};
+
+		let mut candidates: Vec<Vec<u8>> = (0..bytes.len()).map(|len| bytes[..len].to_vec()).collect();
+		for position in 0..bytes.len() {
+			for mask in [0x01, 0x80, 0xff] {
+				let mut corrupted = bytes.clone();
+				corrupted[position] ^= mask;
+				candidates.push(corrupted);
+			}
+		}
+
+		for candidate in candidates {
+			let Ok(index) = Index::from_bytes(&candidate) else {
+				continue;
+			};
+			for query in ["rust", "programing", "écriture", ""] {
+				let _ = search_hits(&index, query, 10);
+				let _ = search_with_options(&index, query, &options);
+			}
+			let _ = index.stats(5);
+			let _ = index.dump();
+			let _ = index.verify();
+		}
+	}
+
+	#[test]
+	fn test_facet_filter_on_mismatched_facet_lists() {
+		let item = InputItem {
+			facets: [("lang".to_string(), vec!["en".to_string()])].into(),
+			..item("doc-1", "rust guide", 90)
+		};
+		let bytes = build_index(vec![item]).unwrap().to_bytes().unwrap();
+
+		// Drops the item list of the only value of `lang`, keeping the value.
+		let facet = b"\x04lang\x01\x02en\x01\x01\x00";
+		let position = bytes
+			.windows(facet.len())
+			.position(|window| window == facet)
+			.unwrap();
+		let mut damaged = bytes[..position].to_vec();
+		damaged.extend_from_slice(b"\x04lang\x01\x02en\x00");
+		damaged.extend_from_slice(&bytes[position + facet.len()..]);
+
+		let index = Index::from_bytes(&damaged).unwrap();
+		let options = SearchOptions {
+			filters: vec![filter("lang", &["en"])],
+			..Default::default()
+		};
+		assert!(
+			search_with_options(&index, "rust", &options)
+				.unwrap()
+				.hits
+				.is_empty()
+		);
+		assert!(
+			index
+				.verify()
+				.unwrap_err()
+				.to_string()
+				.contains("mismatched value lists")
+		);
+	}
+
+	#[test]
+	fn test_verify_accepts_full_id_dictionary() {
+		let json = include_str!("../../demo/build_index/size.json");
+		let items: Vec<InputItem> = serde_json::from_str(json).unwrap();
+		let index = build_index(items).unwrap();
+
+		assert_eq!(index.ids.dict_syms.len(), 255);
+		index.verify().unwrap();
+		assert!(!search(&index, "bike", 100).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_fsst_get_many_and_iter() {
+		let strings = ["alpha", "beta", "gamma", "délta"];
+		let vec = FsstStrVec::from_strings(&strings);
+
+		assert_eq!(vec.get_many([3, 0]).unwrap(), vec!["délta", "alpha"]);
+		assert!(vec.get_many([1, 4]).is_err());
+
+		let all: Vec<String> = vec.iter().collect::<Result<_, _>>().unwrap();
+		assert_eq!(all, strings);
+
+		let deserialized: FsstStrVec =
+			postcard::from_bytes(&postcard::to_allocvec(&vec).unwrap()).unwrap();
+		assert_eq!(deserialized.get(3).as_deref(), Some("délta"));
+	}
+
+	#[test]
+	fn test_top_items_matches_full_sort() {
+		let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+		let mut next = move |bound: u64| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state % bound
+		};
+
+		for round in 0..50 {
+			let item_count = 1 + next(300) as usize;
+			let lists: Vec<Vec<(usize, u8)>> = (0..1 + next(12))
+				.map(|_| {
+					let max_weight = if round % 2 == 0 { 256 } else { 8 };
+					let mut postings: Vec<(usize, u8)> = Vec::new();
+					for item_index in 0..item_count {
+						if next(4) == 0 {
+							postings.push((item_index, next(max_weight) as u8));
+						}
+					}
+					postings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+					postings
+				})
+				.collect();
+
+			let mut expected: std::collections::HashMap<usize, u8> = Default::default();
+			for postings in &lists {
+				for (item_index, weight) in postings {
+					let score = expected.entry(*item_index).or_insert(0);
+					*score = score.saturating_add(*weight);
+				}
+			}
+			let mut expected: Vec<(usize, u8)> = expected.into_iter().collect();
+			expected.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+			let mut expected_matched: Vec<usize> =
+				expected.iter().map(|(item_index, _)| *item_index).collect();
+			expected_matched.sort();
+			let matched = |scores: crate::score::Scores| {
+				let mut matched = scores.matched;
+				matched.sort();
+				matched
+			};
+
+			for k in [0, 1, 2, 3, 10, 50, 1000] {
+				let scores =
+					crate::score::top_items(lists.iter().collect(), item_count, k, None, None).unwrap();
+				assert_eq!(
+					scores.top,
+					expected[..k.min(expected.len())],
+					"round {} k {}",
+					round,
+					k
+				);
+				assert_eq!(matched(scores), expected_matched, "round {} k {}", round, k);
+			}
+
+			let boosts: Vec<Option<f32>> = (0..item_count)
+				.map(|_| (next(3) != 0).then(|| next(40) as f32 / 10.0))
+				.collect();
+			let max = boosts
+				.iter()
+				.flatten()
+				.fold(0.0, |max: f32, &boost| max.max(boost));
+			for mode in [BoostMode::Multiply, BoostMode::Add, BoostMode::Log] {
+				let rank = |(item_index, score): &(usize, u8)| mode.apply(*score, boosts[*item_index]);
+				let mut ranked = expected.clone();
+				ranked.sort_by(|a, b| rank(b).total_cmp(&rank(a)).then_with(|| a.0.cmp(&b.0)));
+
+				let boosts = crate::score::Boosts {
+					mode,
+					boosts: &boosts,
+					max,
+				};
+				for k in [0, 1, 2, 3, 10, 50, 1000] {
+					let scores =
+						crate::score::top_items(lists.iter().collect(), item_count, k, None, Some(&boosts))
+							.unwrap();
+					assert_eq!(
+						scores.top,
+						ranked[..k.min(ranked.len())],
+						"round {} {:?} k {}",
+						round,
+						mode,
+						k
+					);
+					assert_eq!(
+						matched(scores),
+						expected_matched,
+						"round {} {:?} k {}",
+						round,
+						mode,
+						k
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_top_items_rejects_missing_item() {
+		let postings = vec![(0, 10), (5, 5)];
+		assert!(crate::score::top_items(vec![&postings], 3, 10, None, None).is_err());
+
+		// Scores left over from the failed call do not leak into the next.
+		let postings = vec![(1, 7)];
+		let scores = crate::score::top_items(vec![&postings], 3, 10, None, None).unwrap();
+		assert_eq!(scores.top, [(1, 7)]);
+		assert_eq!(scores.matched, [1]);
+	}
+
+	#[test]
+	fn test_max_expansions_prefers_frequent_keywords() {
+		let mut items: Vec<InputItem> = (0..5)
+			.map(|i| item(&format!("apple-{}", i), "apple", 90))
+			.collect();
+		items.push(item("apricot", "apricot", 90));
+		items.push(item("ap", "ap", 90));
+		let index = build_index(items).unwrap();
+
+		let options = SearchOptions {
+			max_results: 20,
+			max_expansions: 1,
+			..Default::default()
+		};
+		let results = search_with_options(&index, "ap", &options).unwrap();
+		let mut ids: Vec<&str> = results.hits.iter().map(|hit| hit.id.as_str()).collect();
+		ids.sort();
+		assert_eq!(
+			ids,
+			vec!["ap", "apple-0", "apple-1", "apple-2", "apple-3", "apple-4"]
+		);
+		assert_eq!(results.stats.query_words, 1);
+		assert_eq!(results.stats.matched_keywords, 3);
+		assert_eq!(results.stats.expanded_keywords, 2);
+
+		let options = SearchOptions {
+			max_expansions: 0,
+			..options
+		};
+		let results = search_with_options(&index, "ap", &options).unwrap();
+		assert_eq!(results.hits.len(), 1);
+		assert_eq!(results.hits[0].id, "ap");
+	}
+
+	#[test]
+	fn test_min_fuzzy_length() {
+		let index = build_index(vec![item("car", "car", 90), item("cart", "cart", 90)]).unwrap();
+
+		let fuzzy = SearchOptions::default();
+		let ids: Vec<String> = search_with_options(&index, "cat", &fuzzy)
+			.unwrap()
+			.hits
+			.into_iter()
+			.map(|hit| hit.id)
+			.collect();
+		assert_eq!(ids, vec!["car", "cart"]);
+
+		let prefix_only = SearchOptions {
+			min_fuzzy_length: 4,
+			..Default::default()
+		};
+		let results = search_with_options(&index, "cat", &prefix_only).unwrap();
+		assert!(results.hits.is_empty());
+		assert_eq!(results.stats.matched_keywords, 0);
+	}
+
+	#[test]
+	fn test_search_pagination() {
+		let index = build_index(sample_items(200)).unwrap();
+		let all = SearchOptions {
+			max_results: 1000,
+			..Default::default()
+		};
+		let full = search_with_options(&index, "rust w", &all).unwrap();
+		assert!(full.hits.len() > 20);
+		assert_eq!(full.total, full.hits.len());
+
+		let mut paged = Vec::new();
+		for page in 0.. {
+			let options = SearchOptions {
+				max_results: 7,
+				offset: page * 7,
+				..Default::default()
+			};
+			let results = search_with_options(&index, "rust w", &options).unwrap();
+			assert_eq!(results.total, full.total);
+			if results.hits.is_empty() {
+				break;
+			}
+			paged.extend(results.hits);
+		}
+		assert_eq!(paged, full.hits);
+
+		let count_only = SearchOptions {
+			max_results: 0,
+			..Default::default()
+		};
+		let results = search_with_options(&index, "rust w", &count_only).unwrap();
+		assert!(results.hits.is_empty());
+		assert_eq!(results.total, full.total);
+	}
+
+	#[test]
+	fn test_multi_index_pagination() {
+		let mut multi = MultiIndex::new();
+		multi.add("a", build_index(sample_items(30)).unwrap());
+		multi.add("b", build_index(sample_items(20)).unwrap());
+
+		let all = SearchOptions {
+			max_results: 1000,
+			..Default::default()
+		};
+		let full = multi.search_with_options("rust w", &all).unwrap();
+		assert_eq!(full.total, full.hits.len());
+
+		let page = SearchOptions {
+			max_results: 5,
+			offset: 10,
+			..Default::default()
+		};
+		let results = multi.search_with_options("rust w", &page).unwrap();
+		assert_eq!(results.total, full.total);
+		assert_eq!(results.hits, full.hits[10..15]);
+	}
+
+	fn named<V>(entries: impl IntoIterator<Item = (&'static str, V)>) -> BTreeMap<String, V> {
+		entries
+			.into_iter()
+			.map(|(name, value)| (name.to_string(), value))
+			.collect()
+	}
+
+	fn strings(values: &[&str]) -> Vec<String> {
+		values.iter().map(|v| v.to_string()).collect()
+	}
+
+	/// Checks that `items` give the same bytes however the index is put
+	/// together: streamed with spilling, merged from two parts, patched by a
+	/// writer or loaded back.
+	fn assert_rebuilds_match(items: &[InputItem]) {
+		let expected = build_index(items.to_vec()).unwrap();
+		assert!(expected.verify().is_ok());
+		let bytes = expected.to_bytes().unwrap();
+
+		let mut builder = StreamingIndexBuilder::with_memory_budget(0, std::env::temp_dir());
+		for item in items.iter().cloned() {
+			builder.add(item).unwrap();
+		}
+		assert_eq!(builder.finish().unwrap().to_bytes().unwrap(), bytes);
+
+		let middle = items.len() / 2;
+		let merged = Index::merge(
+			&[
+				build_index(items[..middle].to_vec()).unwrap(),
+				build_index(items[middle..].to_vec()).unwrap(),
+			],
+			DuplicatePolicy::KeepFirst,
+		)
+		.unwrap();
+		assert_eq!(merged.to_bytes().unwrap(), bytes);
+
+		let mut writer = IndexWriter::open(build_index(items[..middle].to_vec()).unwrap()).unwrap();
+		for item in items[middle..].iter().cloned() {
+			writer.add(item).unwrap();
+		}
+		assert_eq!(writer.finish().unwrap().to_bytes().unwrap(), bytes);
+
+		let roundtrip = Index::from_bytes(&bytes).unwrap();
+		assert!(roundtrip.verify().is_ok());
+		assert_eq!(roundtrip.to_bytes().unwrap(), bytes);
+	}
+
+	fn faceted_items() -> Vec<InputItem> {
+		vec![
+			InputItem {
+				facets: named([("lang", strings(&["en"])), ("kind", strings(&["guide"]))]),
+				..item("a", "rust guide", 90)
+			},
+			InputItem {
+				facets: named([
+					("lang", strings(&["de"])),
+					("kind", strings(&["reference"])),
+				]),
+				..item("b", "rust reference", 90)
+			},
+			InputItem {
+				facets: named([
+					("lang", strings(&["en", "fr"])),
+					("kind", strings(&["guide"])),
+				]),
+				..item("c", "rust tutorial", 90)
+			},
+			InputItem {
+				facets: named([("lang", strings(&["en"])), ("kind", strings(&["guide"]))]),
+				..item("d", "python guide", 90)
+			},
+			item("e", "rust book", 90),
+		]
+	}
+
+	fn filter(facet: &str, values: &[&str]) -> FacetFilter {
+		FacetFilter {
+			facet: facet.to_string(),
+			values: strings(values),
+		}
+	}
+
+	fn hit_ids(hits: &[SearchHit]) -> Vec<String> {
+		let mut ids: Vec<String> = hits.iter().map(|hit| hit.id.clone()).collect();
+		ids.sort();
+		ids
+	}
+
+	#[test]
+	fn test_facet_filters() {
+		let index = build_index(faceted_items()).unwrap();
+		let filtered = |filters: Vec<FacetFilter>| {
+			let options = SearchOptions {
+				filters,
+				..Default::default()
+			};
+			let results = search_with_options(&index, "rust", &options).unwrap();
+			assert_eq!(results.total, results.hits.len());
+			hit_ids(&results.hits)
+		};
+
+		assert_eq!(filtered(vec![]), ["a", "b", "c", "e"]);
+		assert_eq!(filtered(vec![filter("lang", &["en"])]), ["a", "c"]);
+		assert_eq!(filtered(vec![filter("lang", &["de", "fr"])]), ["b", "c"]);
+		assert_eq!(
+			filtered(vec![
+				filter("lang", &["en", "de"]),
+				filter("kind", &["reference"])
+			]),
+			["b"]
+		);
+		assert!(filtered(vec![filter("lang", &["es"])]).is_empty());
+		assert!(filtered(vec![filter("missing", &["en"])]).is_empty());
+		assert!(filtered(vec![filter("lang", &[])]).is_empty());
+	}
+
+	#[test]
+	fn test_facet_counts_cover_all_pages() {
+		let index = build_index(faceted_items()).unwrap();
+		let options = SearchOptions {
+			max_results: 1,
+			facets: vec![
+				"lang".to_string(),
+				"kind".to_string(),
+				"missing".to_string(),
+			],
+			..Default::default()
+		};
+		let results = search_with_options(&index, "rust", &options).unwrap();
+		let count = |value: &str, count| FacetValueCount {
+			value: value.to_string(),
+			count,
+		};
+
+		assert_eq!(results.hits.len(), 1);
+		assert_eq!(results.facets.len(), 3);
+		assert_eq!(results.facets[0].facet, "lang");
+		assert_eq!(
+			results.facets[0].values,
+			[count("en", 2), count("de", 1), count("fr", 1)]
+		);
+		assert_eq!(
+			results.facets[1].values,
+			[count("guide", 2), count("reference", 1)]
+		);
+		assert!(results.facets[2].values.is_empty());
+
+		let filtered = SearchOptions {
+			filters: vec![filter("kind", &["guide"])],
+			..options
+		};
+		let results = search_with_options(&index, "rust", &filtered).unwrap();
+		assert_eq!(results.facets[0].values, [count("en", 2), count("fr", 1)]);
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(faceted_items()).unwrap());
+		multi.add("y", build_index(faceted_items()).unwrap());
+		let results = multi.search_with_options("rust", &filtered).unwrap();
+		assert_eq!(results.total, 4);
+		assert_eq!(results.facets[0].values, [count("en", 4), count("fr", 2)]);
+	}
+
+	#[test]
+	fn test_facets_survive_rebuilds() {
+		let items = faceted_items();
+		assert_rebuilds_match(&items);
+
+		let options = SearchOptions {
+			filters: vec![filter("lang", &["en"])],
+			facets: vec!["kind".to_string()],
+			..Default::default()
+		};
+		let results = |index: &Index| search_with_options(index, "rust guide", &options).unwrap();
+		let updated = InputItem {
+			facets: named([("lang", strings(&["de"]))]),
+			..item("c", "rust tutorial", 90)
+		};
+		let added = InputItem {
+			facets: named([("lang", strings(&["en"])), ("kind", strings(&["book"]))]),
+			..item("f", "rust guide", 90)
+		};
+
+		let mut writer = IndexWriter::open(build_index(items.clone()).unwrap()).unwrap();
+		writer.delete("a");
+		writer.update(updated.clone()).unwrap();
+		writer.add(added.clone()).unwrap();
+		let patched = writer.finish().unwrap();
+		let mut rebuilt_items = items[1..].to_vec();
+		rebuilt_items[1] = updated;
+		rebuilt_items.push(added);
+		let rebuilt = build_index(rebuilt_items).unwrap();
+		assert_eq!(results(&patched), results(&rebuilt));
+		assert_eq!(hit_ids(&results(&patched).hits), ["d", "f"]);
+	}
+
+	#[test]
+	fn test_input_item_facets_accept_single_values() {
+		let json = r#"{"id":"a","searchTerms":[],"facets":{"lang":"en","tags":["x","y"]}}"#;
+		let item: InputItem = serde_json::from_str(json).unwrap();
+
+		assert_eq!(item.facets["lang"], ["en"]);
+		assert_eq!(item.facets["tags"], ["x", "y"]);
+	}
+
+	fn numbered_items() -> Vec<InputItem> {
+		vec![
+			InputItem {
+				numbers: named([("price", 30.0), ("year", 2020.0)]),
+				..item("a", "rust guide", 90)
+			},
+			InputItem {
+				numbers: named([("price", 10.0), ("year", 2024.0)]),
+				..item("b", "rust rust reference", 90)
+			},
+			InputItem {
+				numbers: named([("price", 20.0)]),
+				..item("c", "rust tutorial", 90)
+			},
+			InputItem {
+				numbers: named([("year", 2022.0)]),
+				..item("d", "rust book", 90)
+			},
+			InputItem {
+				numbers: named([("price", 10.0), ("year", f64::NAN)]),
+				..item("e", "rust notes", 90)
+			},
+		]
+	}
+
+	fn sort_key(attribute: &str, descending: bool) -> SortKey {
+		SortKey {
+			attribute: attribute.to_string(),
+			descending,
+		}
+	}
+
+	#[test]
+	fn test_range_filters() {
+		let index = build_index(numbered_items()).unwrap();
+		let ranged = |ranges: Vec<RangeFilter>| {
+			let options = SearchOptions {
+				ranges,
+				..Default::default()
+			};
+			hit_ids(&search_with_options(&index, "rust", &options).unwrap().hits)
+		};
+		let range = |attribute: &str, min: Option<f64>, max: Option<f64>| RangeFilter {
+			attribute: attribute.to_string(),
+			min,
+			max,
+		};
+
+		assert_eq!(
+			ranged(vec![range("price", Some(10.0), Some(20.0))]),
+			["b", "c", "e"]
+		);
+		assert_eq!(ranged(vec![range("price", Some(15.0), None)]), ["a", "c"]);
+		assert_eq!(ranged(vec![range("year", None, None)]), ["a", "b", "d"]);
+		assert_eq!(
+			ranged(vec![
+				range("price", None, Some(20.0)),
+				range("year", Some(2021.0), None)
+			]),
+			["b"]
+		);
+		assert!(ranged(vec![range("weight", None, None)]).is_empty());
+	}
+
+	#[test]
+	fn test_sort_by_attributes() {
+		let index = build_index(numbered_items()).unwrap();
+		let sorted = |index: &Index, sort: Vec<SortKey>| {
+			let options = SearchOptions {
+				sort,
+				..Default::default()
+			};
+			let results = search_with_options(index, "rust", &options).unwrap();
+			results
+				.hits
+				.into_iter()
+				.map(|hit| hit.id)
+				.collect::<Vec<_>>()
+		};
+
+		// Equal prices fall back to the score, missing values come last.
+		assert_eq!(
+			sorted(&index, vec![sort_key("price", false)]),
+			["b", "e", "c", "a", "d"]
+		);
+		assert_eq!(
+			sorted(&index, vec![sort_key("price", true)]),
+			["a", "c", "b", "e", "d"]
+		);
+		assert_eq!(
+			sorted(
+				&index,
+				vec![sort_key("year", true), sort_key("price", false)]
+			),
+			["b", "d", "a", "e", "c"]
+		);
+		assert_eq!(
+			sorted(&index, vec![sort_key("weight", false)]),
+			sorted(&index, vec![])
+		);
+
+		let items = numbered_items();
+		assert_rebuilds_match(&items);
+
+		let page = SearchOptions {
+			max_results: 2,
+			offset: 1,
+			sort: vec![sort_key("price", true)],
+			..Default::default()
+		};
+		let results = search_with_options(&index, "rust", &page).unwrap();
+		assert_eq!(results.total, 5);
+		assert_eq!(hit_ids(&results.hits), ["b", "c"]);
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(items[..2].to_vec()).unwrap());
+		multi.add("y", build_index(items[2..].to_vec()).unwrap());
+		let results = multi.search_with_options("rust", &page).unwrap();
+		let ids: Vec<&str> = results.hits.iter().map(|hit| hit.id.as_str()).collect();
+		assert_eq!(ids, ["c", "b"]);
+	}
+
+	fn boosted_items() -> Vec<InputItem> {
+		vec![
+			item("a", "rust", 200),
+			InputItem {
+				boost: Some(20.0),
+				..item("b", "rust", 50)
+			},
+			InputItem {
+				boost: Some(80.0),
+				..item("c", "rust", 30)
+			},
+			InputItem {
+				boost: Some(0.5),
+				..item("d", "rust", 100)
+			},
+			InputItem {
+				boost: Some(-5.0),
+				..item("e", "rust", 10)
+			},
+		]
+	}
+
+	#[test]
+	fn test_item_boosts() {
+		let index = build_index(boosted_items()).unwrap();
+		let ranked = |index: &Index, boost: BoostMode| {
+			let options = SearchOptions {
+				boost,
+				..Default::default()
+			};
+			let hits = search_with_options(index, "rust", &options).unwrap().hits;
+			hits
+				.into_iter()
+				.map(|hit| (hit.id, hit.score))
+				.collect::<Vec<_>>()
+		};
+		let ids = |hits: Vec<(String, u8)>| hits.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+		// Scores stay the query scores; negative boosts are ignored.
+		let plain = ranked(&index, BoostMode::None);
+		let expected = [("a", 200), ("d", 100), ("b", 50), ("c", 30), ("e", 10)];
+		assert_eq!(plain, expected.map(|(id, score)| (id.to_string(), score)));
+		assert_eq!(
+			ids(ranked(&index, BoostMode::Multiply)),
+			["c", "b", "a", "d", "e"]
+		);
+		assert_eq!(
+			ids(ranked(&index, BoostMode::Add)),
+			["a", "c", "d", "b", "e"]
+		);
+		assert_eq!(
+			ids(ranked(&index, BoostMode::Log)),
+			["b", "a", "c", "d", "e"]
+		);
+
+		let mut sorted = ranked(&index, BoostMode::Multiply);
+		sorted.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+		assert_eq!(sorted, plain);
+
+		let items = boosted_items();
+		assert_rebuilds_match(&items);
+
+		let mut writer = IndexWriter::open(build_index(items.clone()).unwrap()).unwrap();
+		writer
+			.update(InputItem {
+				boost: Some(0.1),
+				..item("a", "rust", 200)
+			})
+			.unwrap();
+		let patched = writer.finish().unwrap();
+		assert_eq!(
+			ids(ranked(&patched, BoostMode::Multiply)),
+			["c", "b", "d", "a", "e"]
+		);
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(items[..2].to_vec()).unwrap());
+		multi.add("y", build_index(items[2..].to_vec()).unwrap());
+		let hits = multi.search("rust", 10).unwrap();
+		let multi_ids: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+		assert_eq!(multi_ids, ["c", "b", "a", "d", "e"]);
+	}
+
+	fn field_items() -> Vec<InputItem> {
+		let term = |raw: &str, weight| (SearchTokens::Raw(raw.to_string()), weight);
+		vec![
+			InputItem {
+				fields: named([("title", vec![term("guide", 50)])]),
+				..item("a", "rust", 50)
+			},
+			InputItem {
+				id: "b".to_string(),
+				fields: named([("body", vec![term("rust guide", 60)])]),
+				..Default::default()
+			},
+			InputItem {
+				id: "c".to_string(),
+				fields: named([
+					("title", vec![term("python", 80)]),
+					("body", vec![term("guide", 40)]),
+				]),
+				..Default::default()
+			},
+		]
+	}
+
+	#[test]
+	fn test_query_term_boosts() {
+		let index = build_index(field_items()).unwrap();
+		let hits = |query: &str| {
+			let hits = search_hits(&index, query, 10).unwrap();
+			hits
+				.into_iter()
+				.map(|hit| (hit.id, hit.score))
+				.collect::<Vec<_>>()
+		};
+		let expected = |hits: &[(&str, u8)]| {
+			hits
+				.iter()
+				.map(|(id, score)| (id.to_string(), *score))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			hits("rust guide"),
+			expected(&[("b", 120), ("a", 100), ("c", 40)])
+		);
+		assert_eq!(
+			hits("rust^3 guide"),
+			expected(&[("b", 240), ("a", 200), ("c", 40)])
+		);
+		assert_eq!(
+			hits("rust guide^0"),
+			expected(&[("b", 60), ("a", 50), ("c", 0)])
+		);
+		assert_eq!(hits("RUST^10"), expected(&[("a", 255), ("b", 255)]));
+		// Multipliers that are not numbers stay part of the word.
+		assert!(hits("rust^x").is_empty());
+	}
+
+	#[test]
+	fn test_field_boosts() {
+		let index = build_index(field_items()).unwrap();
+		let hits = |field_boosts: &[(&str, f32)]| {
+			let options = SearchOptions {
+				field_boosts: field_boosts
+					.iter()
+					.map(|(field, boost)| (field.to_string(), *boost))
+					.collect(),
+				..Default::default()
+			};
+			let hits = search_with_options(&index, "rust guide", &options)
+				.unwrap()
+				.hits;
+			hits
+				.into_iter()
+				.map(|hit| (hit.id, hit.score))
+				.collect::<Vec<_>>()
+		};
+		let expected = |hits: &[(&str, u8)]| {
+			hits
+				.iter()
+				.map(|(id, score)| (id.to_string(), *score))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			hits(&[("missing", 5.0)]),
+			expected(&[("b", 120), ("a", 100), ("c", 40)])
+		);
+		assert_eq!(
+			hits(&[("title", 3.0)]),
+			expected(&[("a", 200), ("b", 120), ("c", 40)])
+		);
+		assert_eq!(
+			hits(&[("title", 2.0), ("body", 0.5)]),
+			expected(&[("a", 150), ("b", 60), ("c", 20)])
+		);
+	}
+
+	#[test]
+	fn test_fields_survive_rebuilds() {
+		// Field numbers do not depend on the order fields are first seen in.
+		let items = field_items();
+		assert_rebuilds_match(&items);
+		assert_rebuilds_match(&[items[1].clone(), items[2].clone(), items[0].clone()]);
+
+		let json = r#"{"id":"a","searchTerms":[],"fields":{"title":[{"type":"raw","value":"Rust","weight":9}]}}"#;
+		let item: InputItem = serde_json::from_str(json).unwrap();
+		assert_eq!(item.fields["title"].len(), 1);
+		assert_eq!(item.fields["title"][0].1, 9);
+	}
+
+	#[test]
+	fn test_writer_rejects_missing_field() {
+		let index = build_index(field_items()).unwrap();
+		let mut postings: Vec<Vec<u8>> = (0..index.keyword_count())
+			.map(|keyword_index| index.fields.posting_fields(keyword_index).to_vec())
+			.collect();
+		*postings.iter_mut().flatten().next().unwrap() = 7;
+		let names = index.fields.names().to_vec();
+		let index = Index {
+			fields: crate::fields::Fields::new(names, postings),
+			..index
+		};
+
+		let bytes = index.to_bytes().unwrap();
+		assert!(
+			Index::from_bytes_verified(&bytes)
+				.unwrap_err()
+				.to_string()
+				.contains("missing field")
+		);
+		let writer = IndexWriter::open(Index::from_bytes(&bytes).unwrap()).unwrap();
+		assert!(
+			writer
+				.finish()
+				.unwrap_err()
+				.to_string()
+				.contains("missing field")
+		);
+	}
+
+	fn grouped_items() -> Vec<InputItem> {
+		vec![
+			InputItem {
+				group: Some("/guide".to_string()),
+				..item("a", "rust", 50)
+			},
+			InputItem {
+				group: Some("/guide".to_string()),
+				..item("b", "rust", 90)
+			},
+			item("c", "rust", 70),
+			InputItem {
+				group: Some("/book".to_string()),
+				..item("d", "rust", 60)
+			},
+			InputItem {
+				group: Some("/guide".to_string()),
+				..item("e", "rust", 80)
+			},
+			item("f", "rust", 40),
+		]
+	}
+
+	#[test]
+	fn test_collapse_groups() {
+		let collapsed = |index: &Index, max_results: usize, offset: usize| {
+			let options = SearchOptions {
+				max_results,
+				offset,
+				collapse: true,
+				..Default::default()
+			};
+			let results = search_with_options(index, "rust", &options).unwrap();
+			let hits = results
+				.hits
+				.into_iter()
+				.map(|hit| (hit.id, hit.collapsed))
+				.collect::<Vec<_>>();
+			(hits, results.total)
+		};
+		let expected = |hits: &[(&str, usize)]| {
+			hits
+				.iter()
+				.map(|(id, collapsed)| (id.to_string(), *collapsed))
+				.collect::<Vec<_>>()
+		};
+
+		let index = build_index(grouped_items()).unwrap();
+		assert!(index.verify().is_ok());
+		assert_eq!(
+			collapsed(&index, 10, 0),
+			(expected(&[("b", 2), ("c", 0), ("d", 0), ("f", 0)]), 4)
+		);
+		assert_eq!(
+			collapsed(&index, 2, 1),
+			(expected(&[("c", 0), ("d", 0)]), 4)
+		);
+
+		let results = search_hits(&index, "rust", 10).unwrap();
+		assert_eq!(results.len(), 6);
+		assert!(results.iter().all(|hit| hit.collapsed == 0));
+
+		// Groups do not depend on how the index was put together.
+		let items = grouped_items();
+		assert_rebuilds_match(&items);
+		assert_rebuilds_match(&[&items[3..], &items[..3]].concat());
+
+		let mut writer = IndexWriter::open(index).unwrap();
+		writer
+			.update(InputItem {
+				group: Some("/book".to_string()),
+				..item("b", "rust", 90)
+			})
+			.unwrap();
+		let patched = writer.finish().unwrap();
+		assert_eq!(
+			collapsed(&patched, 10, 0).0,
+			expected(&[("b", 1), ("e", 1), ("c", 0), ("f", 0)])
+		);
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(items[..3].to_vec()).unwrap());
+		multi.add("y", build_index(items[3..].to_vec()).unwrap());
+		let options = SearchOptions {
+			collapse: true,
+			..Default::default()
+		};
+		let results = multi.search_with_options("rust", &options).unwrap();
+		let hits: Vec<(&str, usize)> = results
+			.hits
+			.iter()
+			.map(|hit| (hit.id.as_str(), hit.collapsed))
+			.collect();
+		// Groups are collapsed within each index.
+		assert_eq!(hits, [("b", 1), ("e", 0), ("c", 0), ("d", 0), ("f", 0)]);
+		assert_eq!(results.total, 5);
+	}
+
+	fn suggest_items() -> Vec<InputItem> {
+		vec![
+			item("a", "rust programming guide", 10),
+			item("b", "rust project layout", 10),
+			item("c", "python programming", 90),
+			item("d", "programming puzzles", 20),
+		]
+	}
+
+	#[test]
+	fn test_suggest() {
+		let index = build_index(suggest_items()).unwrap();
+		assert!(index.verify().is_ok());
+		let completions = |suggestions: Vec<crate::Suggestion>| {
+			suggestions
+				.into_iter()
+				.map(|suggestion| (suggestion.text, suggestion.frequency, suggestion.weight))
+				.collect::<Vec<_>>()
+		};
+		let expected = |suggestions: &[(&str, usize, u64)]| {
+			suggestions
+				.iter()
+				.map(|(text, frequency, weight)| (text.to_string(), *frequency, *weight))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			completions(suggest(&index, "P", 10).unwrap()),
+			expected(&[
+				("programming", 3, 120),
+				("python", 1, 90),
+				("puzzles", 1, 20),
+				("project", 1, 10)
+			])
+		);
+		assert_eq!(
+			completions(suggest(&index, "pro", 10).unwrap()),
+			expected(&[("programming", 3, 120), ("project", 1, 10)])
+		);
+		let by_weight = SuggestOptions {
+			max_suggestions: 1,
+			rank: SuggestRank::Weight,
+		};
+		assert_eq!(
+			completions(suggest_with_options(&index, "p", &by_weight).unwrap()),
+			expected(&[("programming", 3, 120)])
+		);
+
+		// Earlier words restrict the items that count.
+		assert_eq!(
+			completions(suggest(&index, "Rust  pro", 10).unwrap()),
+			expected(&[("Rust  programming", 1, 10), ("Rust  project", 1, 10)])
+		);
+		assert_eq!(
+			completions(suggest(&index, "python pro", 10).unwrap()),
+			expected(&[("python programming", 1, 90)])
+		);
+		assert!(suggest(&index, "rust pu", 10).unwrap().is_empty());
+		assert!(suggest(&index, "haskell pro", 10).unwrap().is_empty());
+		assert!(suggest(&index, "rust ", 10).unwrap().is_empty());
+		assert!(suggest(&index, "", 10).unwrap().is_empty());
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(suggest_items()[..2].to_vec()).unwrap());
+		multi.add("y", build_index(suggest_items()[2..].to_vec()).unwrap());
+		let options = SuggestOptions::default();
+		assert_eq!(
+			completions(multi.suggest("pro", &options).unwrap()),
+			expected(&[("programming", 3, 120), ("project", 1, 10)])
+		);
+	}
+
+	#[test]
+	fn test_suggest_limit_keeps_best() {
+		// Equally frequent keywords are told apart by weight, even when the
+		// better one comes later.
+		let items = vec![
+			item("a", "rust pa", 5),
+			item("b", "pa", 5),
+			item("c", "rust pb", 25),
+			item("d", "pb", 25),
+		];
+		let index = build_index(items).unwrap();
+		assert_eq!(suggest(&index, "p", 1).unwrap()[0].keyword, "pb");
+		assert_eq!(suggest(&index, "rust p", 1).unwrap()[0].keyword, "pb");
+
+		let index = build_index(sample_items(300)).unwrap();
+		for rank in [SuggestRank::Frequency, SuggestRank::Weight] {
+			let suggestions = |query: &str, max_suggestions: usize| {
+				let options = SuggestOptions {
+					max_suggestions,
+					rank,
+				};
+				suggest_with_options(&index, query, &options).unwrap()
+			};
+			for query in ["1", "w", "rust 1", "guide w", "b"] {
+				let all = suggestions(query, usize::MAX);
+				let key = |suggestion: &crate::Suggestion| match rank {
+					SuggestRank::Frequency => (suggestion.frequency as u64, suggestion.weight),
+					SuggestRank::Weight => (suggestion.weight, suggestion.frequency as u64),
+				};
+				let sorted = all
+					.windows(2)
+					.all(|pair| (key(&pair[1]), &pair[0].keyword) < (key(&pair[0]), &pair[1].keyword));
+				assert!(sorted);
+				for max_suggestions in [0, 1, 2, 3, 5, 100] {
+					let top = suggestions(query, max_suggestions);
+					assert_eq!(
+						top,
+						all[..max_suggestions.min(all.len())],
+						"{:?} {}",
+						query,
+						max_suggestions
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_spelling_corrections() {
+		let index = build_index(suggest_items()).unwrap();
+		let correction = |query: &str| {
+			search_with_options(&index, query, &SearchOptions::default())
+				.unwrap()
+				.correction
+		};
+
+		assert_eq!(correction("rust"), None);
+		// Typos within one edit already match, so corrections cover two.
+		assert_eq!(correction("pyhtn"), Some("python".to_string()));
+		assert_eq!(correction("Prjcet^2"), Some("project^2".to_string()));
+		// Words without a close keyword or too short to correct are kept.
+		assert_eq!(correction("xyzzy"), None);
+		assert_eq!(correction("rsu  zz"), Some("rust  zz".to_string()));
+		assert_eq!(correction(""), None);
+
+		// Edits are counted as for searching.
+		let options = SearchOptions {
+			transpositions: false,
+			..Default::default()
+		};
+		let results = search_with_options(&index, "rsut gudie", &options).unwrap();
+		assert_eq!(results.correction, Some("rust guide".to_string()));
+
+		// The keyword in the most items wins among equally close ones.
+		let words = |raw: &str| item(raw, raw, 90);
+		let index = build_index(vec![words("card"), words("cart"), words("cart tart")]).unwrap();
+		let results = search_with_options(&index, "cxrx", &SearchOptions::default()).unwrap();
+		assert_eq!(results.correction, Some("cart".to_string()));
+
+		// Nothing is proposed while something matches.
+		let index = build_index(suggest_items()).unwrap();
+		let results = search_with_options(&index, "rust layot", &SearchOptions::default()).unwrap();
+		assert_eq!(results.total, 2);
+		assert_eq!(results.correction, None);
+
+		let mut multi = MultiIndex::new();
+		multi.add("x", build_index(suggest_items()[..2].to_vec()).unwrap());
+		multi.add("y", build_index(suggest_items()[2..].to_vec()).unwrap());
+		let results = multi
+			.search_with_options("pyhtn", &SearchOptions::default())
+			.unwrap();
+		assert_eq!(results.correction, Some("python".to_string()));
+	}
+
+	#[test]
+	fn test_typo_tolerance() {
+		let index = build_index(suggest_items()).unwrap();
+		let found = |query: &str, options: &SearchOptions| {
+			let hits = search_with_options(&index, query, options).unwrap().hits;
+			hit_ids(&hits)
+		};
+		let defaults = SearchOptions::default();
+		let no_transpositions = SearchOptions {
+			transpositions: false,
+			..Default::default()
+		};
+		let longer_two_typos = SearchOptions {
+			min_two_typo_length: 10,
+			..Default::default()
+		};
+
+		// Swapped characters are a single edit unless disabled.
+		assert_eq!(found("pyhton", &defaults), ["c"]);
+		assert!(found("pyhton", &no_transpositions).is_empty());
+		// Long enough words match within two edits.
+		assert_eq!(found("prgrammng", &defaults), ["a", "c", "d"]);
+		assert!(found("prgrammng", &longer_two_typos).is_empty());
+		assert!(found("pyhtn", &defaults).is_empty());
+
+		let automaton = crate::typo::EditDistance::new("café", 2, true);
+		assert_eq!(automaton.distance("café"), Some(0));
+		assert_eq!(automaton.distance("cafe"), Some(1));
+		assert_eq!(automaton.distance("acfé"), Some(1));
+		assert_eq!(automaton.distance("caféteria"), None);
+		assert_eq!(
+			crate::typo::EditDistance::new("ab", 2, false).distance("ba"),
+			Some(2)
+		);
+	}
+
+	#[test]
+	fn test_phonetic_keys() {
+		use crate::phonetic::phonetic_key;
+
+		let key = |word: &str| phonetic_key(word).unwrap();
+		assert_eq!(key("Johnson"), "JNSN");
+		assert_eq!(key("jonson"), key("johnson"));
+		assert_eq!(key("philip"), key("fillip"));
+		assert_eq!(key("night"), key("nite"));
+		assert_eq!(key("knight"), "NT");
+		assert_eq!(key("catherine"), key("kathryn"));
+		assert_eq!(key("xavier"), "SFR");
+		assert_eq!(phonetic_key("123"), None);
+	}
+
+	#[test]
+	fn test_phonetic_matching() {
+		let items = vec![
+			item("a", "philip johnson", 90),
+			item("b", "night shift", 90),
+			item("c", "fillet knife", 90),
+		];
+		let phonetic = BuildOptions {
+			phonetic: true,
+			..Default::default()
+		};
+		let index = build_index_with_options(items.clone(), phonetic).unwrap();
+		assert!(index.verify().is_ok());
+		let hits = |index: &Index, query: &str, phonetic_weight: f32| {
+			let options = SearchOptions {
+				phonetic_weight,
+				..Default::default()
+			};
+			let hits = search_with_options(index, query, &options).unwrap().hits;
+			hits
+				.into_iter()
+				.map(|hit| (hit.id, hit.score))
+				.collect::<Vec<_>>()
+		};
+
+		// Matches by sound are weighted down, and only used for words no
+		// other way matched.
+		assert_eq!(hits(&index, "fillip", 0.5), [("a".to_string(), 45)]);
+		assert_eq!(hits(&index, "nite", 1.0), [("b".to_string(), 90)]);
+		assert_eq!(hits(&index, "jonson", 0.5), [("a".to_string(), 90)]);
+		assert!(hits(&index, "fillip", 0.0).is_empty());
+		let plain = build_index(items.clone()).unwrap();
+		assert!(hits(&plain, "fillip", 0.5).is_empty());
+		assert_eq!(plain.stats(0).unwrap().sections.phonetic, 2);
+
+		let mut builder =
+			StreamingIndexBuilder::with_memory_budget(0, std::env::temp_dir()).with_options(phonetic);
+		for item in items.iter().cloned() {
+			builder.add(item).unwrap();
+		}
+		assert_eq!(
+			builder.finish().unwrap().to_bytes().unwrap(),
+			index.to_bytes().unwrap()
+		);
+
+		let merged = Index::merge(
+			&[
+				build_index_with_options(items[..1].to_vec(), phonetic).unwrap(),
+				build_index_with_options(items[1..].to_vec(), phonetic).unwrap(),
+			],
+			DuplicatePolicy::KeepFirst,
+		)
+		.unwrap();
+		assert_eq!(merged.to_bytes().unwrap(), index.to_bytes().unwrap());
+
+		let mut writer =
+			IndexWriter::open(build_index_with_options(items[..2].to_vec(), phonetic).unwrap()).unwrap();
+		writer.add(items[2].clone()).unwrap();
+		assert_eq!(
+			writer.finish().unwrap().to_bytes().unwrap(),
+			index.to_bytes().unwrap()
+		);
+	}
+
+	#[test]
+	fn test_pattern_queries() {
+		let index = build_index(vec![
+			item("a", "programming v2", 90),
+			item("b", "progressing v10", 90),
+			item("c", "program vx", 90),
+			item("d", "café v", 90),
+		])
+		.unwrap();
+		let found = |query: &str| hit_ids(&search_hits(&index, query, 10).unwrap());
+
+		assert_eq!(found("prog*ing"), ["a", "b"]);
+		assert_eq!(found("PROG**MING"), ["a"]);
+		assert_eq!(found("prog*"), ["a", "b", "c"]);
+		assert_eq!(found("/v[0-9]+/"), ["a", "b"]);
+		assert_eq!(found("/^v\\d$/"), ["a"]);
+		assert_eq!(found("/v[^0-9]?/"), ["c", "d"]);
+		assert_eq!(found("/caf./"), ["d"]);
+		assert_eq!(found("/pro.ram(ming)?/"), Vec::<String>::new());
+		// Patterns not starting with a character are plain words.
+		assert!(found("*ing").is_empty());
+		assert!(found("/.*/").is_empty());
+
+		let options = SearchOptions {
+			patterns: false,
+			..Default::default()
+		};
+		assert!(
+			search_with_options(&index, "prog*ing", &options)
+				.unwrap()
+				.hits
+				.is_empty()
+		);
+
+		let options = SearchOptions {
+			max_expansions: 1,
+			..Default::default()
+		};
+		let results = search_with_options(&index, "/v.*/", &options).unwrap();
+		assert_eq!(results.stats.matched_keywords, 4);
+		assert_eq!(results.stats.expanded_keywords, 1);
+		assert_eq!(results.correction, None);
+		let results = search_with_options(&index, "prog*xyz", &SearchOptions::default()).unwrap();
+		assert_eq!(results.total, 0);
+		assert_eq!(results.correction, None);
+
+		// Escapes keep their case; unsupported ones leave a plain word.
+		let index = build_index(vec![
+			item("a", "a1", 90),
+			item("b", "ab", 90),
+			item("c", "a_", 90),
+		])
+		.unwrap();
+		let found = |query: &str| hit_ids(&search_hits(&index, query, 10).unwrap());
+		assert_eq!(found("/a\\d/"), ["a"]);
+		assert_eq!(found("/A\\w/"), ["a", "b", "c"]);
+		assert_eq!(found("/A[B-Z]/"), ["b"]);
+		assert!(found("/a\\D/").is_empty());
+		assert!(found("/a\\W/").is_empty());
+		assert!(crate::pattern::Pattern::parse("/a\\D/").is_none());
+
+		let long = format!("a{}", "b?".repeat(200));
+		assert!(crate::pattern::Pattern::parse(&format!("/{}/", long)).is_none());
+	}
 }
@@ -0,0 +1,134 @@
+use fst::Automaton;
+
+/// Matches keywords within an edit distance of a query word, optionally
+/// counting a swap of two adjacent characters as a single edit.
+///
+/// Unlike `fst::automaton::Levenshtein`, which builds a full DFA up front and
+/// can take long or fail for longer words and distances, the distance is
+/// computed one character at a time as the FST is walked, so building the
+/// automaton costs nothing and each step is linear in the length of the word.
+/// Branches are abandoned as soon as every alignment exceeds the distance.
+#[derive(Debug)]
+pub(crate) struct EditDistance {
+	word: Vec<char>,
+	max_distance: u32,
+	transpositions: bool,
+}
+
+/// Progress through a keyword: the last two rows of the distance table and
+/// the bytes of a character not yet complete. `None` once nothing can match.
+#[derive(Debug, Clone)]
+pub(crate) struct EditState {
+	row: Vec<u32>,
+	previous_row: Vec<u32>,
+	last: Option<char>,
+	pending: Vec<u8>,
+}
+
+impl EditDistance {
+	pub fn new(word: &str, max_distance: u32, transpositions: bool) -> Self {
+		EditDistance {
+			word: word.chars().collect(),
+			max_distance,
+			transpositions,
+		}
+	}
+
+	/// The edit distance from the word to `keyword`, if within the maximum.
+	pub fn distance(&self, keyword: &str) -> Option<u32> {
+		let mut state = self.start();
+		for &byte in keyword.as_bytes() {
+			state = self.accept(&state, byte);
+		}
+		let state = state?;
+		Some(state.row[self.word.len()]).filter(|&distance| distance <= self.max_distance)
+	}
+
+	/// The row after reading `c`, given the row before.
+	fn step(&self, state: &EditState, c: char) -> Vec<u32> {
+		let mut row = Vec::with_capacity(self.word.len() + 1);
+		row.push(state.row[0] + 1);
+		for (j, &w) in self.word.iter().enumerate() {
+			let mut distance = (state.row[j] + u32::from(w != c))
+				.min(state.row[j + 1] + 1)
+				.min(row[j] + 1);
+			if self.transpositions && j > 0 && state.last == Some(w) && self.word[j - 1] == c && w != c {
+				distance = distance.min(state.previous_row[j - 1] + 1);
+			}
+			row.push(distance);
+		}
+		row
+	}
+}
+
+impl Automaton for EditDistance {
+	type State = Option<EditState>;
+
+	fn start(&self) -> Self::State {
+		Some(EditState {
+			row: (0..=self.word.len() as u32).collect(),
+			previous_row: Vec::new(),
+			last: None,
+			pending: Vec::new(),
+		})
+	}
+
+	fn is_match(&self, state: &Self::State) -> bool {
+		state.as_ref().is_some_and(|state| {
+			state.pending.is_empty() && state.row[self.word.len()] <= self.max_distance
+		})
+	}
+
+	fn can_match(&self, state: &Self::State) -> bool {
+		state.is_some()
+	}
+
+	fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+		let state = state.as_ref()?;
+		let c = match utf8_step(&state.pending, byte) {
+			Utf8Step::Char(c) => c,
+			Utf8Step::Partial(pending) => {
+				return Some(EditState {
+					pending,
+					..state.clone()
+				});
+			}
+			Utf8Step::Invalid => return None,
+		};
+
+		let row = self.step(state, c);
+		if row
+			.iter()
+			.min()
+			.is_some_and(|&distance| distance > self.max_distance)
+		{
+			return None;
+		}
+		Some(EditState {
+			previous_row: state.row.clone(),
+			row,
+			last: Some(c),
+			pending: Vec::new(),
+		})
+	}
+}
+
+/// What reading one more byte of a keyword gives, for automata that work on
+/// characters rather than bytes.
+pub(crate) enum Utf8Step {
+	Char(char),
+	/// The bytes read so far of a character not yet complete.
+	Partial(Vec<u8>),
+	Invalid,
+}
+
+/// Adds `byte` to the `pending` bytes of an incomplete character.
+pub(crate) fn utf8_step(pending: &[u8], byte: u8) -> Utf8Step {
+	let mut bytes = pending.to_vec();
+	bytes.push(byte);
+	match std::str::from_utf8(&bytes) {
+		Ok(s) => s.chars().next().map_or(Utf8Step::Invalid, Utf8Step::Char),
+		Err(e) if e.error_len().is_none() => Utf8Step::Partial(bytes),
+		Err(_) => Utf8Step::Invalid,
+	}
+}
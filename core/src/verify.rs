@@ -0,0 +1,171 @@
+use crate::{FsstStrVec, Index};
+
+/// Whether `codes` only refers to symbols in a table of `symbol_count`
+/// entries, with every escape code followed by a literal byte.
+pub(crate) fn valid_fsst_codes(codes: &[u8], symbol_count: usize) -> bool {
+	let mut codes = codes.iter();
+	while let Some(&code) = codes.next() {
+		if code == fsst::ESCAPE_CODE {
+			if codes.next().is_none() {
+				return false;
+			}
+		} else if code as usize >= symbol_count {
+			return false;
+		}
+	}
+	true
+}
+
+impl FsstStrVec {
+	/// Checks that the symbol table is one the FSST decompressor accepts.
+	pub(crate) fn check_dictionary(&self) -> Result<(), Box<dyn std::error::Error>> {
+		if self.dict_syms.len() != self.dict_lens.len() {
+			return Err(
+				format!(
+					"ID dictionary has {} symbols but {} lengths",
+					self.dict_syms.len(),
+					self.dict_lens.len()
+				)
+				.into(),
+			);
+		}
+		if self.dict_syms.len() > fsst::ESCAPE_CODE as usize {
+			return Err(
+				format!(
+					"ID dictionary has too many symbols: {}",
+					self.dict_syms.len()
+				)
+				.into(),
+			);
+		}
+		if let Some(len) = self.dict_lens.iter().find(|len| !(1..=8).contains(*len)) {
+			return Err(format!("ID dictionary has a symbol of length {}", len).into());
+		}
+		Ok(())
+	}
+
+	/// Checks the symbol table and offsets, and that every string decodes to
+	/// UTF-8.
+	fn verify(&self) -> Result<(), Box<dyn std::error::Error>> {
+		self.check_dictionary()?;
+
+		let decomp = fsst::Decompressor::new(&self.symbols, &self.dict_lens);
+
+		let mut end = self.data.len();
+		for (i, &start) in self.offsets.iter().enumerate().rev() {
+			let start = start as usize;
+			if start > end {
+				return Err(format!("ID {} has an offset out of bounds", i).into());
+			}
+			let codes = &self.data[start..end];
+			if !valid_fsst_codes(codes, self.symbols.len()) {
+				return Err(format!("ID {} contains invalid FSST codes", i).into());
+			}
+			if std::str::from_utf8(&decomp.decompress(codes)).is_err() {
+				return Err(format!("ID {} is not valid UTF-8", i).into());
+			}
+			end = start;
+		}
+		Ok(())
+	}
+}
+
+impl Index {
+	/// Checks that the index is internally consistent, so that searching it
+	/// cannot fail on corrupted data.
+	///
+	/// Verifies the FST checksum, that every FST value refers to a posting list,
+	/// that every posting refers to an item, that all item IDs decode to UTF-8,
+	/// and finally the content hash.
+	pub fn verify(&self) -> Result<(), Box<dyn std::error::Error>> {
+		use fst::Streamer;
+
+		self.ids.verify()?;
+
+		let map = self.verified_fst()?;
+
+		if map.len() != self.keyword_to_items.len() {
+			return Err(
+				format!(
+					"FST has {} keywords but there are {} posting lists",
+					map.len(),
+					self.keyword_to_items.len()
+				)
+				.into(),
+			);
+		}
+		let mut stream = map.stream();
+		while let Some((keyword, keyword_index)) = stream.next() {
+			if keyword_index as usize >= self.keyword_to_items.len() {
+				return Err(
+					format!(
+						"Keyword '{}' refers to missing posting list {}",
+						String::from_utf8_lossy(keyword),
+						keyword_index
+					)
+					.into(),
+				);
+			}
+		}
+
+		for (keyword_index, postings) in self.keyword_to_items.iter().enumerate() {
+			if let Some((item_index, _)) = postings
+				.iter()
+				.find(|(item_index, _)| *item_index >= self.len())
+			{
+				return Err(
+					format!(
+						"Posting list {} refers to missing item {}",
+						keyword_index, item_index
+					)
+					.into(),
+				);
+			}
+		}
+
+		if self.keyword_weights != crate::suggest::keyword_weights(&self.keyword_to_items) {
+			return Err("Keyword weights do not match the posting lists".into());
+		}
+
+		self.phonetic.check()?;
+		let phonetic = if self.build_options.phonetic {
+			crate::phonetic::PhoneticIndex::new(&self.fst)?
+		} else {
+			Default::default()
+		};
+		if self.phonetic != phonetic {
+			return Err("Phonetic keys do not match the keywords".into());
+		}
+
+		self.fields.verify(&self.keyword_to_items)?;
+		self.attributes.verify(self.len())?;
+
+		if !self.has_valid_content_hash() {
+			return Err("Content hash does not match the index contents".into());
+		}
+		Ok(())
+	}
+
+	/// Opens the FST after checking its checksum, since traversing a corrupted
+	/// FST can panic.
+	pub(crate) fn verified_fst(&self) -> Result<fst::Map<&[u8]>, Box<dyn std::error::Error>> {
+		let map = fst::Map::new(self.fst.as_slice())?;
+		if let Err(e) = map.as_fst().verify() {
+			let e: &dyn std::error::Error = match &e {
+				fst::Error::Fst(e) => e,
+				e => e,
+			};
+			return Err(format!("FST is corrupted: {}", e).into());
+		}
+		Ok(map)
+	}
+
+	/// Like [`Index::from_bytes`], but also runs [`Index::verify`] on the result.
+	///
+	/// Use this for indexes from untrusted sources, such as the network.
+	pub fn from_bytes_verified(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+		let index = Self::from_bytes(bytes)?;
+		index.verify()?;
+		Ok(index)
+	}
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::{
+	AttributesBuilder, FsstStrVec, Index, InputItem, ItemAttributes, build_index_with_options,
+	merge_segments,
+};
+
+/// Applies additions, updates and deletions to an existing [`Index`] without
+/// re-tokenizing the items it already contains.
+///
+/// Deleted and replaced items are tombstoned; [`IndexWriter::finish`] then
+/// builds a segment for the pending items and merges it with the surviving
+/// postings of the base index into a new FST.
+pub struct IndexWriter {
+	base: Index,
+	slots: Vec<Slot>,
+	positions: HashMap<String, usize>,
+}
+
+enum Slot {
+	Base(usize),
+	Pending(InputItem),
+	Deleted,
+}
+
+impl IndexWriter {
+	pub fn open(base: Index) -> Result<Self, Box<dyn std::error::Error>> {
+		let mut slots = Vec::with_capacity(base.len());
+		let mut positions = HashMap::with_capacity(base.len());
+
+		for (item_index, id) in base.ids.iter().enumerate() {
+			positions.insert(id?, slots.len());
+			slots.push(Slot::Base(item_index));
+		}
+
+		Ok(IndexWriter {
+			base,
+			slots,
+			positions,
+		})
+	}
+
+	/// Adds a new item, failing if an item with the same ID exists.
+	pub fn add(&mut self, item: InputItem) -> Result<(), Box<dyn std::error::Error>> {
+		if self.positions.contains_key(&item.id) {
+			return Err(format!("Item '{}' already exists", item.id).into());
+		}
+		self.upsert(item);
+		Ok(())
+	}
+
+	/// Replaces an existing item in place, failing if no item has its ID.
+	pub fn update(&mut self, item: InputItem) -> Result<(), Box<dyn std::error::Error>> {
+		if !self.positions.contains_key(&item.id) {
+			return Err(format!("Item '{}' does not exist", item.id).into());
+		}
+		self.upsert(item);
+		Ok(())
+	}
+
+	/// Replaces the item with the same ID, or appends it if there is none.
+	pub fn upsert(&mut self, item: InputItem) {
+		match self.positions.get(&item.id) {
+			Some(&position) => self.slots[position] = Slot::Pending(item),
+			None => {
+				self.positions.insert(item.id.clone(), self.slots.len());
+				self.slots.push(Slot::Pending(item));
+			}
+		}
+	}
+
+	/// Deletes the item with the given ID, returning whether it existed.
+	pub fn delete(&mut self, id: &str) -> bool {
+		match self.positions.remove(id) {
+			Some(position) => {
+				self.slots[position] = Slot::Deleted;
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Number of items the new index will contain.
+	pub fn len(&self) -> usize {
+		self
+			.slots
+			.iter()
+			.filter(|slot| !matches!(slot, Slot::Deleted))
+			.count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Writes the new index.
+	///
+	/// Surviving items keep their relative order, updated items keep their
+	/// position and added items are appended.
+	pub fn finish(self) -> Result<Index, Box<dyn std::error::Error>> {
+		let mut ids: Vec<String> = Vec::with_capacity(self.len());
+		let mut base_attributes = self.base.attributes.items(self.base.len());
+		let mut attributes = AttributesBuilder::new();
+		let mut base_remap: Vec<Option<usize>> = vec![None; self.base.len()];
+		let mut pending: Vec<InputItem> = Vec::new();
+		let mut pending_remap: Vec<Option<usize>> = Vec::new();
+
+		for slot in self.slots {
+			match slot {
+				Slot::Base(item_index) => {
+					base_remap[item_index] = Some(ids.len());
+					ids.push(
+						self
+							.base
+							.ids
+							.try_get(item_index)?
+							.ok_or("Failed to get item id")?,
+					);
+					attributes.push(std::mem::take(&mut base_attributes[item_index]));
+				}
+				Slot::Pending(item) => {
+					pending_remap.push(Some(ids.len()));
+					ids.push(item.id.clone());
+					attributes.push(ItemAttributes::from(&item));
+					pending.push(item);
+				}
+				Slot::Deleted => {}
+			}
+		}
+
+		let segment = build_index_with_options(pending, self.base.build_options)?;
+		let (fst, keyword_to_items, fields) =
+			merge_segments(&[(&self.base, base_remap), (&segment, pending_remap)])?;
+
+		Index::new(
+			fst,
+			FsstStrVec::from_strings(&ids),
+			keyword_to_items,
+			fields,
+			attributes.finish(),
+			self.base.build_options,
+		)
+	}
+}
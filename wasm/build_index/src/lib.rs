@@ -1,4 +1,6 @@
-use docfind_core::build_index;
+use docfind_core::{
+	BuildOptions, Index, IndexWriter, StreamingIndexBuilder, build_index, build_index_with_options,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -19,3 +21,87 @@ pub fn build(documents_json: &str) -> Result<Vec<u8>, JsValue> {
 		.to_bytes()
 		.map_err(|e| JsValue::from_str(&format!("Failed to serialize index: {}", e)))
 }
+
+/// Like `build`, with build options given as JSON such as
+/// `{"keywordMerge": "max"}`.
+#[wasm_bindgen(js_name = buildWithOptions)]
+pub fn build_with_options(documents_json: &str, options_json: &str) -> Result<Vec<u8>, JsValue> {
+	let items: Vec<docfind_core::InputItem> = serde_json::from_str(documents_json)
+		.map_err(|e| JsValue::from_str(&format!("Failed to parse JSON: {}", e)))?;
+	let options: BuildOptions = serde_json::from_str(options_json)
+		.map_err(|e| JsValue::from_str(&format!("Failed to parse build options: {}", e)))?;
+
+	let index = build_index_with_options(items, options)
+		.map_err(|e| JsValue::from_str(&format!("Failed to build index: {}", e)))?;
+
+	index
+		.to_bytes()
+		.map_err(|e| JsValue::from_str(&format!("Failed to serialize index: {}", e)))
+}
+
+/// Builds an index from newline-delimited JSON, one item per line, without
+/// materializing the whole corpus as a single JSON document.
+#[wasm_bindgen(js_name = buildNdjson)]
+pub fn build_ndjson(documents_ndjson: &str) -> Result<Vec<u8>, JsValue> {
+	let mut builder = StreamingIndexBuilder::new();
+	builder
+		.add_ndjson(documents_ndjson.as_bytes())
+		.map_err(|e| JsValue::from_str(&format!("Failed to parse NDJSON: {}", e)))?;
+
+	let index = builder
+		.finish()
+		.map_err(|e| JsValue::from_str(&format!("Failed to build index: {}", e)))?;
+
+	index
+		.to_bytes()
+		.map_err(|e| JsValue::from_str(&format!("Failed to serialize index: {}", e)))
+}
+
+#[wasm_bindgen]
+pub fn patch(
+	index_bytes: &[u8],
+	documents_json: &str,
+	deleted_ids: Vec<String>,
+) -> Result<Vec<u8>, JsValue> {
+	let index = Index::from_bytes_verified(index_bytes)
+		.map_err(|e| JsValue::from_str(&format!("Failed to deserialize index: {}", e)))?;
+
+	let items: Vec<docfind_core::InputItem> = serde_json::from_str(documents_json)
+		.map_err(|e| JsValue::from_str(&format!("Failed to parse JSON: {}", e)))?;
+
+	let mut writer = IndexWriter::open(index)
+		.map_err(|e| JsValue::from_str(&format!("Failed to open index: {}", e)))?;
+
+	for id in &deleted_ids {
+		writer.delete(id);
+	}
+	for item in items {
+		writer.upsert(item);
+	}
+
+	let index = writer
+		.finish()
+		.map_err(|e| JsValue::from_str(&format!("Failed to build index: {}", e)))?;
+
+	index
+		.to_bytes()
+		.map_err(|e| JsValue::from_str(&format!("Failed to serialize index: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Both sides run on the host here; the core suite, run on wasm32-wasip1 in
+	// CI, checks that builds do not depend on the target.
+	#[test]
+	fn test_build_matches_build_index() {
+		let documents = include_str!("../../../demo/build_index/documents.json");
+
+		let wasm_bytes = build(documents).unwrap();
+		let items: Vec<docfind_core::InputItem> = serde_json::from_str(documents).unwrap();
+		let native_bytes = build_index(items).unwrap().to_bytes().unwrap();
+
+		assert_eq!(wasm_bytes, native_bytes);
+	}
+}
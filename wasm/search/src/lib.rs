@@ -1,4 +1,6 @@
-use docfind_core::Index;
+use docfind_core::{
+	Index, MultiIndex, MultiSearchResults, SearchOptions, SuggestOptions, Suggestion,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -7,27 +9,209 @@ extern "C" {
 	fn log(msg: &str);
 }
 
+const DEFAULT_SOURCE: &str = "default";
+
+#[derive(serde::Serialize)]
+struct SearchPage {
+	ids: Vec<String>,
+	total: usize,
+	correction: Option<String>,
+}
+
 #[wasm_bindgen]
 pub struct WasmIndex {
-	inner: Index,
+	inner: MultiIndex,
+}
+
+fn load_index(index_bytes: &[u8]) -> Result<Index, JsValue> {
+	Index::from_bytes(index_bytes)
+		.map_err(|e| JsValue::from_str(&format!("Failed to deserialize index: {}", e)))
+}
+
+/// Missing values, such as the `correction` of a search that matched, become
+/// `null` rather than `undefined`.
+const SERIALIZER: serde_wasm_bindgen::Serializer =
+	serde_wasm_bindgen::Serializer::new().serialize_missing_as_null(true);
+
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+	serde::Serialize::serialize(value, &SERIALIZER)
+		.map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
 }
 
 #[wasm_bindgen]
 impl WasmIndex {
 	#[wasm_bindgen(constructor)]
-	pub fn new(index_bytes: &[u8]) -> Result<WasmIndex, JsValue> {
-		let index = Index::from_bytes(index_bytes)
-			.map_err(|e| JsValue::from_str(&format!("Failed to deserialize index: {}", e)))?;
+	pub fn new(index_bytes: &[u8], name: Option<String>) -> Result<WasmIndex, JsValue> {
+		let mut inner = MultiIndex::new();
+		inner.add(
+			name.unwrap_or_else(|| DEFAULT_SOURCE.to_string()),
+			load_index(index_bytes)?,
+		);
+
+		Ok(WasmIndex { inner })
+	}
+
+	/// Loads another index to be searched alongside the existing ones.
+	#[wasm_bindgen(js_name = addIndex)]
+	pub fn add_index(&mut self, name: String, index_bytes: &[u8]) -> Result<(), JsValue> {
+		self.inner.add(name, load_index(index_bytes)?);
+		Ok(())
+	}
+
+	#[wasm_bindgen(js_name = removeIndex)]
+	pub fn remove_index(&mut self, name: &str) -> bool {
+		self.inner.remove(name).is_some()
+	}
+
+	/// Checks the named index, or every loaded one, with `Index::verify`,
+	/// failing with the first problem found. Loading does not check indexes,
+	/// so call this before searching indexes that may be damaged.
+	pub fn verify(&self, name: Option<String>) -> Result<(), JsValue> {
+		let names: Vec<String> = match name {
+			Some(name) => vec![name],
+			None => self.inner.names().map(str::to_string).collect(),
+		};
+		for name in names {
+			self
+				.source(Some(name.clone()))?
+				.verify()
+				.map_err(|e| JsValue::from_str(&format!("Index '{}' is invalid: {}", name, e)))?;
+		}
+		Ok(())
+	}
+
+	/// Searches all loaded indexes, returning `{ ids, total, correction }`
+	/// where `total` counts the matches on all pages and `correction` is a
+	/// corrected query offered when nothing matched.
+	pub fn search(
+		&self,
+		query: &str,
+		max_results: Option<usize>,
+		offset: Option<usize>,
+	) -> Result<JsValue, JsValue> {
+		let results = self.search_page(query, max_results, offset)?;
+
+		to_js(&SearchPage {
+			ids: results.hits.into_iter().map(|hit| hit.id).collect(),
+			total: results.total,
+			correction: results.correction,
+		})
+	}
+
+	/// Like `search`, returning `{ hits, total, correction }` with
+	/// `{ source, id, score, collapsed }` hits.
+	#[wasm_bindgen(js_name = searchHits)]
+	pub fn search_hits(
+		&self,
+		query: &str,
+		max_results: Option<usize>,
+		offset: Option<usize>,
+	) -> Result<JsValue, JsValue> {
+		to_js(&self.search_page(query, max_results, offset)?)
+	}
 
-		Ok(WasmIndex { inner: index })
+	/// Like `searchHits`, taking an options object such as
+	/// `{ maxResults: 20, filters: [{ facet: "lang", values: ["en"] }], facets: ["lang"] }`
+	/// and also returning the requested facet counts.
+	#[wasm_bindgen(js_name = searchWithOptions)]
+	pub fn search_with_options(&self, query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+		let options: SearchOptions = if options.is_undefined() || options.is_null() {
+			SearchOptions::default()
+		} else {
+			serde_wasm_bindgen::from_value(options)
+				.map_err(|e| JsValue::from_str(&format!("Invalid search options: {}", e)))?
+		};
+
+		to_js(&self.run_search(query, &options)?)
+	}
+
+	/// Completions of the last word of `query` from all loaded indexes, as
+	/// `[{ text, keyword, frequency, weight }]` with the most frequent first.
+	pub fn suggest(&self, query: &str, max_suggestions: Option<usize>) -> Result<JsValue, JsValue> {
+		let options = SuggestOptions {
+			max_suggestions: max_suggestions.unwrap_or(10),
+			..Default::default()
+		};
+
+		to_js(&self.run_suggest(query, &options)?)
 	}
 
-	pub fn search(&self, query: &str, max_results: Option<usize>) -> Result<Vec<String>, JsValue> {
-		return docfind_core::search(
-			&self.inner,
-			query,
-			max_results.unwrap_or(10),
-		)
-		.map_err(|e| JsValue::from_str(&format!("Search failed: {}", e)));
+	/// Like `suggest`, taking an options object such as
+	/// `{ maxSuggestions: 5, rank: "weight" }`.
+	#[wasm_bindgen(js_name = suggestWithOptions)]
+	pub fn suggest_with_options(&self, query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+		let options: SuggestOptions = if options.is_undefined() || options.is_null() {
+			SuggestOptions::default()
+		} else {
+			serde_wasm_bindgen::from_value(options)
+				.map_err(|e| JsValue::from_str(&format!("Invalid suggest options: {}", e)))?
+		};
+
+		to_js(&self.run_suggest(query, &options)?)
+	}
+
+	/// Statistics for the named index, or the first one loaded.
+	pub fn stats(&self, name: Option<String>, top_n: Option<usize>) -> Result<JsValue, JsValue> {
+		let stats = self
+			.source(name)?
+			.stats(top_n.unwrap_or(20))
+			.map_err(|e| JsValue::from_str(&format!("Failed to collect stats: {}", e)))?;
+
+		to_js(&stats)
+	}
+
+	/// Every keyword of the named index, or the first one loaded, with its postings.
+	pub fn dump(&self, name: Option<String>) -> Result<JsValue, JsValue> {
+		let dump = self
+			.source(name)?
+			.dump()
+			.map_err(|e| JsValue::from_str(&format!("Failed to dump index: {}", e)))?;
+
+		to_js(&dump)
+	}
+
+	fn search_page(
+		&self,
+		query: &str,
+		max_results: Option<usize>,
+		offset: Option<usize>,
+	) -> Result<MultiSearchResults, JsValue> {
+		let options = SearchOptions {
+			max_results: max_results.unwrap_or(10),
+			offset: offset.unwrap_or(0),
+			..Default::default()
+		};
+
+		self.run_search(query, &options)
+	}
+
+	fn run_search(
+		&self,
+		query: &str,
+		options: &SearchOptions,
+	) -> Result<MultiSearchResults, JsValue> {
+		self
+			.inner
+			.search_with_options(query, options)
+			.map_err(|e| JsValue::from_str(&format!("Search failed: {}", e)))
+	}
+
+	fn run_suggest(&self, query: &str, options: &SuggestOptions) -> Result<Vec<Suggestion>, JsValue> {
+		self
+			.inner
+			.suggest(query, options)
+			.map_err(|e| JsValue::from_str(&format!("Suggest failed: {}", e)))
+	}
+
+	fn source(&self, name: Option<String>) -> Result<&Index, JsValue> {
+		let name = match name {
+			Some(name) => name,
+			None => self.inner.names().next().unwrap_or_default().to_string(),
+		};
+
+		self
+			.inner
+			.get(&name)
+			.ok_or_else(|| JsValue::from_str(&format!("No index named '{}'", name)))
 	}
 }